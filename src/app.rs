@@ -1,4 +1,4 @@
-use clap::{crate_version, App, AppSettings, Arg};
+use clap::{crate_version, App, AppSettings, Arg, SubCommand};
 use std::env::var_os;
 
 pub fn build_app() -> App<'static, 'static> {
@@ -14,6 +14,7 @@ pub fn build_app() -> App<'static, 'static> {
         .usage("asbru --schema <path> --output <path>")
         .setting(clap_color_setting)
         .setting(AppSettings::DeriveDisplayOrder)
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("schema")
                 .long("schema")
@@ -40,6 +41,27 @@ pub fn build_app() -> App<'static, 'static> {
                 .takes_value(true)
                 .help("Should point to a .toml config file")
                 .required(true),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Check the schema against the config without generating anything")
+                .usage("asbru validate --schema <path> --config <path>")
+                .arg(
+                    Arg::with_name("schema")
+                        .long("schema")
+                        .short("s")
+                        .takes_value(true)
+                        .help("Select the schema.graphql file to validate")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("config")
+                        .long("config")
+                        .short("c")
+                        .takes_value(true)
+                        .help("Should point to a .toml config file")
+                        .required(true),
+                ),
         );
 
     app
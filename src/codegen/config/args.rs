@@ -0,0 +1,166 @@
+use serde_json::Value;
+
+/// Where the value of a method argument comes from.
+///
+/// - `"id"`: the GraphQL argument `id`, or the field `id` of an input object argument.
+/// - `"parent.ownerId"`: the field `ownerId` of the parent object.
+/// - `"const:available"` or any value which is not a string: a constant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgumentSource {
+    Argument(String),
+    Parent(String),
+    Constant(Value),
+}
+
+impl ArgumentSource {
+    pub fn parse(value: &Value) -> Self {
+        match value {
+            Value::String(source) => {
+                if let Some(field) = source.strip_prefix("parent.") {
+                    ArgumentSource::Parent(field.to_string())
+                } else if let Some(constant) = source.strip_prefix("const:") {
+                    ArgumentSource::Constant(Value::String(constant.to_string()))
+                } else {
+                    ArgumentSource::Argument(source.to_string())
+                }
+            }
+            constant => ArgumentSource::Constant(constant.clone()),
+        }
+    }
+}
+
+/// Mapping of method arguments, by path, to their source.
+/// A path with dots (`filters.userIds`) targets a nested field of a body argument.
+#[derive(Debug, Clone, Default)]
+pub struct ArgumentsMapping(Vec<(String, ArgumentSource)>);
+
+impl ArgumentsMapping {
+    /// Create a mapping from a JSON object, nested objects are flattened into paths.
+    pub fn from_json(value: &Value) -> Self {
+        let mut mapping = Vec::new();
+        flatten("", value, &mut mapping);
+        ArgumentsMapping(mapping)
+    }
+
+    /// Merge two mappings, the paths of `other` take precedence.
+    pub fn merge(&self, other: &ArgumentsMapping) -> ArgumentsMapping {
+        let mut mapping: Vec<(String, ArgumentSource)> = self
+            .0
+            .iter()
+            .filter(|(path, _)| !other.0.iter().any(|(x, _)| x == path))
+            .cloned()
+            .collect();
+        mapping.extend(other.0.iter().cloned());
+        ArgumentsMapping(mapping)
+    }
+
+    /// Source of a top level argument.
+    pub fn get(&self, argument: &str) -> Option<&ArgumentSource> {
+        self.0
+            .iter()
+            .find(|(path, _)| path == argument)
+            .map(|(_, source)| source)
+    }
+
+    /// Sources of the nested fields of an argument, by their path relative to the argument.
+    pub fn nested(&self, argument: &str) -> Vec<(&str, &ArgumentSource)> {
+        let prefix = format!("{}.", argument);
+        self.0
+            .iter()
+            .filter_map(|(path, source)| path.strip_prefix(&prefix).map(|x| (x, source)))
+            .collect()
+    }
+
+    /// Top level arguments targeted by the mapping.
+    pub fn roots(&self) -> Vec<&str> {
+        let mut roots: Vec<&str> = Vec::new();
+        for (path, _) in self.0.iter() {
+            let root = path.split('.').next().unwrap_or(path);
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+        roots
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, mapping: &mut Vec<(String, ArgumentSource)>) {
+    match value {
+        Value::Object(fields) => fields.iter().for_each(|(key, value)| {
+            let path = if prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            flatten(&path, value, mapping)
+        }),
+        source if !prefix.is_empty() => {
+            mapping.push((prefix.to_string(), ArgumentSource::parse(source)))
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_argument_source() {
+        assert_eq!(
+            ArgumentSource::parse(&json!("id")),
+            ArgumentSource::Argument("id".to_string())
+        );
+        assert_eq!(
+            ArgumentSource::parse(&json!("parent.ownerId")),
+            ArgumentSource::Parent("ownerId".to_string())
+        );
+        assert_eq!(
+            ArgumentSource::parse(&json!("const:available")),
+            ArgumentSource::Constant(json!("available"))
+        );
+        assert_eq!(
+            ArgumentSource::parse(&json!(10)),
+            ArgumentSource::Constant(json!(10))
+        );
+    }
+
+    #[test]
+    fn test_nested_mapping() {
+        let mapping = ArgumentsMapping::from_json(&json!({
+            "ownerId": "parent.id",
+            "filters": { "status": "const:available", "tags": "tags" }
+        }));
+
+        assert_eq!(
+            mapping.get("ownerId"),
+            Some(&ArgumentSource::Parent("id".to_string()))
+        );
+        assert_eq!(mapping.get("filters"), None);
+        assert_eq!(
+            mapping.nested("filters"),
+            vec![
+                ("status", &ArgumentSource::Constant(json!("available"))),
+                ("tags", &ArgumentSource::Argument("tags".to_string())),
+            ]
+        );
+        assert_eq!(mapping.roots(), vec!["filters", "ownerId"]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let config = ArgumentsMapping::from_json(&json!({ "id": "petId", "limit": 10 }));
+        let directive = ArgumentsMapping::from_json(&json!({ "id": "parent.petId" }));
+        let mapping = config.merge(&directive);
+
+        assert_eq!(
+            mapping.get("id"),
+            Some(&ArgumentSource::Parent("petId".to_string()))
+        );
+        assert_eq!(
+            mapping.get("limit"),
+            Some(&ArgumentSource::Constant(json!(10)))
+        );
+    }
+}
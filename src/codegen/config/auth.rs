@@ -0,0 +1,238 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Where the keys validating the tokens come from.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum AuthKey {
+    /// A shared secret, read from the environment variable `env` when the server starts.
+    Secret { env: String },
+    /// A local JWKS file, read when the server starts. Its path is relative to where the server
+    /// runs.
+    Jwks { path: String },
+}
+
+/// Authentication of the callers with a JWT bearer token, from the `[auth]` section.
+///
+/// ```toml
+/// [auth]
+/// key = { type = "Jwks", path = "keys/jwks.json" }
+/// issuer = "https://auth.example.com/"
+/// audience = "pets-api"
+/// ```
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Auth {
+    pub key: AuthKey,
+    /// Accepted algorithms, `HS256` with a secret and `RS256` with a JWKS by default.
+    #[serde(default)]
+    pub algorithms: Vec<String>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    /// Claim listing the scopes of the caller, as a list or a space separated string.
+    #[serde(default = "default_scopes_claim")]
+    pub scopes_claim: String,
+}
+
+fn default_scopes_claim() -> String {
+    "scope".to_string()
+}
+
+impl Auth {
+    fn algorithms(&self) -> Vec<String> {
+        match (&self.key, self.algorithms.is_empty()) {
+            (_, false) => self.algorithms.clone(),
+            (AuthKey::Secret { .. }, true) => vec!["HS256".to_string()],
+            (AuthKey::Jwks { .. }, true) => vec!["RS256".to_string()],
+        }
+    }
+
+    /// Generate the `infrastructure/auth.rs` module: the authenticator validating the tokens,
+    /// the claims of a request and the guard checking the scopes required by `@scope`.
+    pub fn generate_auth_module(&self) -> String {
+        let keys = match &self.key {
+            AuthKey::Secret { env } => format!(
+                r#"let secret = std::env::var({env:?}).map_err(|_| anyhow::anyhow!("{env} is not set"))?;
+        let keys = vec![(None, DecodingKey::from_secret(secret.as_bytes()))];"#,
+                env = env
+            ),
+            AuthKey::Jwks { path } => format!(
+                r#"let jwks = std::fs::read_to_string({path:?})?;
+        let jwks: jsonwebtoken::jwk::JwkSet = serde_json::from_str(&jwks)?;
+        let keys = jwks
+            .keys
+            .iter()
+            .map(|jwk| Ok((jwk.common.key_id.clone(), DecodingKey::from_jwk(jwk)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;"#,
+                path = path
+            ),
+        };
+
+        let algorithms = self
+            .algorithms()
+            .iter()
+            .map(|x| format!("Algorithm::{}", x))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let issuer = match &self.issuer {
+            Some(issuer) => format!("\n        validation.set_issuer(&[{:?}]);", issuer),
+            None => "".to_string(),
+        };
+        let audience = match &self.audience {
+            Some(audience) => format!("\n        validation.set_audience(&[{:?}]);", audience),
+            None => "".to_string(),
+        };
+
+        format!(
+            r#"use async_graphql::guard::Guard;
+use async_graphql::{{Context, Result}};
+use jsonwebtoken::{{Algorithm, DecodingKey, Validation}};
+use once_cell::sync::OnceCell;
+
+use crate::infrastructure::headers::IncomingHeaders;
+
+/// Validate the bearer tokens of the callers, built once at startup and stored inside the schema
+/// data.
+pub struct Authenticator {{
+    /// Keys by id, a key without id validates every token.
+    keys: Vec<(Option<String>, DecodingKey)>,
+    validation: Validation,
+}}
+
+/// Build the authenticator from the `[auth]` config.
+pub fn authenticator() -> anyhow::Result<Authenticator> {{
+    Authenticator::new()
+}}
+
+impl Authenticator {{
+    fn new() -> anyhow::Result<Self> {{
+        {keys}
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.algorithms = vec![{algorithms}];{issuer}{audience}
+
+        Ok(Authenticator {{ keys, validation }})
+    }}
+
+    /// Validate a token and read its claims.
+    pub fn validate(&self, token: &str) -> anyhow::Result<Claims> {{
+        let header = jsonwebtoken::decode_header(token)?;
+        let key = self
+            .keys
+            .iter()
+            .find(|(id, _)| id.is_none() || *id == header.kid)
+            .map(|(_, key)| key)
+            .ok_or_else(|| anyhow::anyhow!("no key validates the token"))?;
+        let claims = jsonwebtoken::decode::<serde_json::Map<String, serde_json::Value>>(
+            token,
+            key,
+            &self.validation,
+        )?
+        .claims;
+
+        Ok(Claims::new(claims))
+    }}
+}}
+
+/// Claims of an authenticated caller.
+#[derive(Clone, Debug)]
+pub struct Claims {{
+    pub subject: Option<String>,
+    pub scopes: Vec<String>,
+    /// Every claim of the token.
+    pub claims: serde_json::Map<String, serde_json::Value>,
+}}
+
+impl Claims {{
+    fn new(claims: serde_json::Map<String, serde_json::Value>) -> Self {{
+        let subject = claims
+            .get("sub")
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_string());
+        let scopes = match claims.get({scopes_claim:?}) {{
+            Some(serde_json::Value::String(scopes)) => {{
+                scopes.split_whitespace().map(|x| x.to_string()).collect()
+            }}
+            Some(serde_json::Value::Array(scopes)) => scopes
+                .iter()
+                .filter_map(|x| x.as_str())
+                .map(|x| x.to_string())
+                .collect(),
+            _ => Vec::new(),
+        }};
+
+        Claims {{
+            subject,
+            scopes,
+            claims,
+        }}
+    }}
+}}
+
+/// Authentication of a request, stored inside the request data by the server. The token is
+/// validated once, the first time the claims are needed.
+pub struct Authentication {{
+    token: Option<String>,
+    claims: OnceCell<std::result::Result<Claims, String>>,
+}}
+
+impl Authentication {{
+    /// Read the bearer token of the `authorization` header.
+    pub fn new(headers: &IncomingHeaders) -> Self {{
+        let token = headers
+            .0
+            .get("authorization")
+            .and_then(|x| x.strip_prefix("Bearer "))
+            .map(|x| x.trim().to_string());
+
+        Authentication {{
+            token,
+            claims: OnceCell::new(),
+        }}
+    }}
+
+    pub fn claims(&self, authenticator: &Authenticator) -> Result<&Claims> {{
+        let token = self.token.as_ref().ok_or("Unauthenticated")?;
+        self.claims
+            .get_or_init(|| {{
+                authenticator
+                    .validate(token)
+                    .map_err(|e| format!("Invalid token: {{}}", e))
+            }})
+            .as_ref()
+            .map_err(|e| e.clone().into())
+    }}
+}}
+
+/// Claims of the caller, an error when the request is not authenticated.
+pub fn claims<'a>(ctx: &'a Context<'_>) -> Result<&'a Claims> {{
+    ctx.data_opt::<Authentication>()
+        .ok_or("Unauthenticated")?
+        .claims(ctx.data_unchecked::<Authenticator>())
+}}
+
+/// Guard generated by `@scope` and `@auth`: the caller must be authenticated with every scope.
+pub struct ScopeGuard {{
+    pub scopes: &'static [&'static str],
+}}
+
+#[async_trait::async_trait]
+impl Guard for ScopeGuard {{
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {{
+        let claims = claims(ctx)?;
+        match self
+            .scopes
+            .iter()
+            .find(|scope| !claims.scopes.iter().any(|x| x == *scope))
+        {{
+            Some(scope) => Err(format!("Forbidden, the {{}} scope is required", scope).into()),
+            None => Ok(()),
+        }}
+    }}
+}}
+"#,
+            keys = keys,
+            algorithms = algorithms,
+            issuer = issuer,
+            audience = audience,
+            scopes_claim = self.scopes_claim
+        )
+    }
+}
@@ -0,0 +1,141 @@
+use codegen::Scope;
+use convert_case::{Case, Casing};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The client of a service, built once at startup and shared by every resolver.
+///
+/// ```toml
+/// [services.pets.client]
+/// timeout_ms = 5000
+/// pool_max_idle_per_host = 32
+/// user_agent = "pets-api/0.1"
+/// headers = { "x-api-key" = "${PETS_API_KEY}", "x-client" = "gateway" }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// Timeout of a call to the service.
+    pub timeout_ms: Option<u64>,
+    /// Idle connections kept for each host, HTTP only.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// HTTP only.
+    pub user_agent: Option<String>,
+    /// Headers sent with every call, HTTP only. `${VARIABLE}` inside a value is replaced by the
+    /// environment variable when the client is built.
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Name of the client type of a service, registered inside the schema data.
+pub fn client_type(service_name: &str) -> String {
+    format!("{}Client", service_name.to_case(Case::Pascal))
+}
+
+/// Push the client type of a service inside its service file, wrapping the `Client` of its
+/// transport so every service has its own entry inside the schema data.
+pub fn push_client_type(scope: &mut Scope, service_name: &str) {
+    scope.raw(&format!(
+        "/// Client of the `{}` service, shared through the schema data.\n#[derive(Clone)]\npub struct {}(pub Client);",
+        service_name,
+        client_type(service_name)
+    ));
+}
+
+impl ClientConfig {
+    /// Lines building the `reqwest` client of a HTTP service inside `client`. The `env_headers`
+    /// are read from their environment variable, by header name, and sent like `headers`.
+    pub fn http_client_lines(&self, env_headers: &BTreeMap<String, String>) -> String {
+        let mut lines = vec!["let mut headers = reqwest::header::HeaderMap::new();".to_string()];
+        for (name, value) in self.headers.iter() {
+            lines.push(format!(
+                "headers.insert(reqwest::header::HeaderName::from_bytes({:?}.as_bytes())?, reqwest::header::HeaderValue::from_str({})?);",
+                name.to_lowercase(),
+                header_value(value)
+            ));
+        }
+        for (name, variable) in env_headers.iter() {
+            lines.push(format!(
+                "headers.insert(reqwest::header::HeaderName::from_bytes({name:?}.as_bytes())?, reqwest::header::HeaderValue::from_str(&std::env::var({variable:?}).map_err(|_| anyhow::anyhow!(\"{variable} is not set\"))?)?);",
+                name = name.to_lowercase(),
+                variable = variable
+            ));
+        }
+
+        let mut builder = vec!["let client = Client::builder()".to_string()];
+        builder.push("    .default_headers(headers)".to_string());
+        if let Some(timeout) = self.timeout_ms {
+            builder.push(format!(
+                "    .timeout(std::time::Duration::from_millis({}))",
+                timeout
+            ));
+        }
+        if let Some(pool) = self.pool_max_idle_per_host {
+            builder.push(format!("    .pool_max_idle_per_host({})", pool));
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder.push(format!("    .user_agent({:?})", user_agent));
+        }
+        builder.push("    .build()?;".to_string());
+        lines.push(builder.join("\n"));
+
+        lines.join("\n")
+    }
+
+    /// Expression of the `tonic` endpoint of a gRPC service.
+    pub fn grpc_endpoint(&self, endpoint: &str) -> String {
+        match self.timeout_ms {
+            Some(timeout) => format!(
+                "Endpoint::from_static(\"{}\").timeout(std::time::Duration::from_millis({}))",
+                endpoint, timeout
+            ),
+            None => format!("Endpoint::from_static(\"{}\")", endpoint),
+        }
+    }
+}
+
+/// Expression of a static header value, the `${VARIABLE}` inside it are read from the environment.
+fn header_value(value: &str) -> String {
+    let mut parts = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        if start > 0 {
+            parts.push(format!("{:?}.to_string()", &rest[..start]));
+        }
+        let variable = &rest[start + 2..end];
+        parts.push(format!(
+            "std::env::var({variable:?}).map_err(|_| anyhow::anyhow!(\"{variable} is not set\"))?",
+            variable = variable
+        ));
+        rest = &rest[end + 1..];
+    }
+
+    if parts.is_empty() {
+        return format!("{:?}", value);
+    }
+    if !rest.is_empty() {
+        parts.push(format!("{:?}.to_string()", rest));
+    }
+    format!("&[{}].concat()", parts.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_header_value() {
+        assert_eq!(header_value("gateway"), "\"gateway\"");
+        assert_eq!(
+            header_value("${API_KEY}"),
+            "&[std::env::var(\"API_KEY\").map_err(|_| anyhow::anyhow!(\"API_KEY is not set\"))?].concat()"
+        );
+        assert_eq!(
+            header_value("Bearer ${TOKEN}!"),
+            "&[\"Bearer \".to_string(), std::env::var(\"TOKEN\").map_err(|_| anyhow::anyhow!(\"TOKEN is not set\"))?, \"!\".to_string()].concat()"
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use codegen::Scope;
+use convert_case::{Case, Casing};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How the errors of a service are turned into GraphQL errors, on a HTTP transport or one of its
+/// methods: the mapping of a method overrides the one of its service.
+///
+/// ```toml
+/// [services.pets.transport.info.errors]
+/// codes = { 404 = "PET_NOT_FOUND", 409 = "PET_ALREADY_EXISTS" }
+/// pass_body = true
+/// redact = ["password", "token"]
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct ErrorMapping {
+    /// GraphQL error codes by upstream HTTP status, on top of the default ones.
+    pub codes: BTreeMap<String, String>,
+    /// Pass the body of the upstream error inside the `upstream` extension.
+    pub pass_body: Option<bool>,
+    /// Fields of the passed body replaced by `[REDACTED]`, at any depth.
+    pub redact: Vec<String>,
+}
+
+impl ErrorMapping {
+    /// The mapping of a method, on top of the mapping of its service.
+    pub fn merge(&self, method: Option<&ErrorMapping>) -> ErrorMapping {
+        let method = match method {
+            Some(method) => method,
+            None => return self.clone(),
+        };
+
+        let mut codes = self.codes.clone();
+        codes.extend(method.codes.clone());
+        let mut redact = self.redact.clone();
+        redact.extend(
+            method
+                .redact
+                .iter()
+                .filter(|x| !self.redact.contains(x))
+                .cloned(),
+        );
+
+        ErrorMapping {
+            codes,
+            pass_body: method.pass_body.or(self.pass_body),
+            redact,
+        }
+    }
+
+    /// HTTP statuses of `codes` which are not valid statuses.
+    pub fn invalid_statuses(&self) -> Vec<&str> {
+        self.codes
+            .keys()
+            .filter(|x| !matches!(x.parse::<u16>(), Ok(100..=599)))
+            .map(|x| x.as_str())
+            .collect()
+    }
+
+    /// Push the `ErrorPolicy` constant of a method called by `function_name` inside the scope,
+    /// and return its name.
+    pub fn generate_policy(
+        &self,
+        service_name: &str,
+        method_name: &str,
+        function_name: &str,
+        scope: &mut Scope,
+    ) -> String {
+        scope.import("crate::infrastructure::errors", "ErrorPolicy");
+        scope.import("crate::infrastructure::errors", "ServiceError");
+
+        let name = format!("{}_ERRORS", function_name.to_case(Case::UpperSnake));
+        let codes = self
+            .codes
+            .iter()
+            .filter_map(|(status, code)| {
+                status
+                    .parse::<u16>()
+                    .ok()
+                    .map(|status| format!("({}, {:?})", status, code))
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        let redact = self
+            .redact
+            .iter()
+            .map(|x| format!("{:?}", x))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        scope.raw(&format!(
+            r#"/// Mapping of the errors of `{service}.{method}`.
+const {name}: ErrorPolicy = ErrorPolicy {{
+    service: "{service}",
+    method: "{method}",
+    codes: &[{codes}],
+    pass_body: {pass_body},
+    redact: &[{redact}],
+}};"#,
+            service = service_name,
+            method = method_name,
+            name = name,
+            codes = codes,
+            pass_body = self.pass_body.unwrap_or(false),
+            redact = redact
+        ));
+
+        name
+    }
+}
+
+/// Generate the `infrastructure/errors.rs` module: the errors of the services and their mapping
+/// into GraphQL errors.
+pub fn generate_errors_module() -> String {
+    let mut scope = Scope::new();
+    scope.import("async_graphql", "Error");
+    scope.import("async_graphql", "ErrorExtensions");
+    scope.raw(
+        r#"/// Failure of a call to a service, turned into a GraphQL error with the `code`, `service`,
+/// `method` and `status` extensions, and the `upstream` body when it's passed through.
+#[derive(Clone, Debug)]
+pub struct ServiceError {
+    pub code: String,
+    pub service: &'static str,
+    pub method: &'static str,
+    /// Status of the upstream response, HTTP or gRPC.
+    pub status: Option<i32>,
+    /// The upstream object doesn't exist, nullable fields resolve to `null`.
+    pub not_found: bool,
+    /// The service is unavailable, the call can be retried.
+    pub retryable: bool,
+    /// The service failed, counted by the circuit breaker of the method.
+    pub failure: bool,
+    pub message: String,
+    pub upstream: Option<serde_json::Value>,
+}
+
+impl std::fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}: {}", self.service, self.method, self.message)
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl ServiceError {
+    pub fn to_graphql(&self) -> Error {
+        Error::new(self.message.clone()).extend_with(|_, extensions| {
+            extensions.set("code", self.code.clone());
+            extensions.set("service", self.service);
+            extensions.set("method", self.method);
+            if let Some(status) = self.status {
+                extensions.set("status", status);
+            }
+            if let Some(upstream) = self
+                .upstream
+                .clone()
+                .and_then(|x| async_graphql::Value::from_json(x).ok())
+            {
+                extensions.set("upstream", upstream);
+            }
+        })
+    }
+}
+
+/// How the errors of a method are mapped, generated from the config.
+pub struct ErrorPolicy {
+    pub service: &'static str,
+    pub method: &'static str,
+    /// GraphQL error codes by upstream HTTP status, on top of the default ones.
+    pub codes: &'static [(u16, &'static str)],
+    pub pass_body: bool,
+    pub redact: &'static [&'static str],
+}
+
+impl ErrorPolicy {
+    fn error(&self, code: &str, status: Option<i32>, message: String) -> ServiceError {
+        ServiceError {
+            code: code.to_string(),
+            service: self.service,
+            method: self.method,
+            status,
+            not_found: false,
+            retryable: false,
+            failure: false,
+            message,
+            upstream: None,
+        }
+    }
+
+    /// The service can't be reached, or the response can't be read.
+    pub fn transport(&self, timeout: bool, error: impl std::fmt::Display) -> ServiceError {
+        let code = if timeout { "TIMEOUT" } else { "UNAVAILABLE" };
+        let mut error = self.error(
+            code,
+            None,
+            format!("The {} service is unavailable: {}", self.service, error),
+        );
+        error.retryable = true;
+        error.failure = true;
+
+        error
+    }
+
+    /// The circuit breaker of the method is open, the service is not called.
+    pub fn circuit_open(&self) -> ServiceError {
+        self.error(
+            "UNAVAILABLE",
+            None,
+            format!("The {} service is unavailable, its circuit is open", self.service),
+        )
+    }
+
+    /// The request can't be built from the arguments.
+    pub fn bad_request(&self, error: impl std::fmt::Display) -> ServiceError {
+        self.error(
+            "BAD_REQUEST",
+            None,
+            format!("The request to the {} service can't be built: {}", self.service, error),
+        )
+    }
+
+    /// The response doesn't match the expected type.
+    pub fn bad_response(&self, error: impl std::fmt::Display) -> ServiceError {
+        let mut error = self.error(
+            "BAD_RESPONSE",
+            None,
+            format!("The {} service answered an unexpected response: {}", self.service, error),
+        );
+        error.failure = true;
+
+        error
+    }
+
+    /// The service answered with an error status and this `body`.
+    pub fn status(&self, status: u16, body: &str) -> ServiceError {
+        let code = self
+            .codes
+            .iter()
+            .find(|(x, _)| *x == status)
+            .map(|(_, code)| *code)
+            .unwrap_or_else(|| http_code(status));
+
+        let mut error = self.error(
+            code,
+            Some(status.into()),
+            format!("The {} service answered with the status {}", self.service, status),
+        );
+        error.not_found = status == 404;
+        error.retryable = matches!(status, 502 | 503 | 504);
+        error.failure = status >= 500;
+        if self.pass_body {
+            let body = serde_json::from_str(body)
+                .unwrap_or_else(|_| serde_json::Value::String(body.to_string()));
+            error.upstream = Some(redact(body, self.redact));
+        }
+
+        error
+    }
+
+    /// The gRPC service answered with an error status, its `message` is the passed body.
+    pub fn grpc(&self, status: i32, message: &str) -> ServiceError {
+        let mut error = self.error(
+            grpc_code(status),
+            Some(status),
+            format!("The {} service answered with the status {}", self.service, status),
+        );
+        error.not_found = status == 5;
+        error.retryable = matches!(status, 4 | 14);
+        // UNKNOWN, DEADLINE_EXCEEDED, INTERNAL, UNAVAILABLE and DATA_LOSS.
+        error.failure = matches!(status, 2 | 4 | 13 | 14 | 15);
+        if self.pass_body {
+            error.upstream = Some(serde_json::Value::String(message.to_string()));
+        }
+
+        error
+    }
+}
+
+fn http_code(status: u16) -> &'static str {
+    match status {
+        400 | 422 => "BAD_REQUEST",
+        401 => "UNAUTHENTICATED",
+        403 => "FORBIDDEN",
+        404 => "NOT_FOUND",
+        409 => "CONFLICT",
+        429 => "RATE_LIMITED",
+        503 => "UNAVAILABLE",
+        504 => "TIMEOUT",
+        _ => "UPSTREAM_ERROR",
+    }
+}
+
+fn grpc_code(status: i32) -> &'static str {
+    match status {
+        3 | 9 | 11 => "BAD_REQUEST",
+        4 => "TIMEOUT",
+        5 => "NOT_FOUND",
+        6 | 10 => "CONFLICT",
+        7 => "FORBIDDEN",
+        8 => "RATE_LIMITED",
+        14 => "UNAVAILABLE",
+        16 => "UNAUTHENTICATED",
+        _ => "UPSTREAM_ERROR",
+    }
+}
+
+/// Replace the `fields` of the body by `[REDACTED]`, at any depth.
+fn redact(body: serde_json::Value, fields: &[&str]) -> serde_json::Value {
+    match body {
+        serde_json::Value::Object(object) => serde_json::Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| {
+                    if fields.contains(&key.as_str()) {
+                        (key, serde_json::Value::String("[REDACTED]".to_string()))
+                    } else {
+                        (key, redact(value, fields))
+                    }
+                })
+                .collect(),
+        ),
+        serde_json::Value::Array(values) => serde_json::Value::Array(
+            values.into_iter().map(|x| redact(x, fields)).collect(),
+        ),
+        value => value,
+    }
+}"#,
+    );
+
+    scope.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mapping(toml_str: &str) -> ErrorMapping {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let service = mapping(
+            r#"
+            codes = { 404 = "PET_NOT_FOUND", 409 = "CONFLICT" }
+            pass_body = true
+            redact = ["password"]
+            "#,
+        );
+        let method = mapping(
+            r#"
+            codes = { 409 = "PET_ALREADY_EXISTS" }
+            redact = ["password", "token"]
+            "#,
+        );
+        let merged = service.merge(Some(&method));
+
+        assert_eq!(merged.codes["404"], "PET_NOT_FOUND");
+        assert_eq!(merged.codes["409"], "PET_ALREADY_EXISTS");
+        assert_eq!(merged.pass_body, Some(true));
+        assert_eq!(merged.redact, vec!["password", "token"]);
+    }
+
+    #[test]
+    fn test_invalid_statuses() {
+        let mapping = mapping(r#"codes = { 404 = "NOT_FOUND", 42 = "NOPE", teapot = "TEA" }"#);
+
+        assert_eq!(mapping.invalid_statuses(), vec!["42", "teapot"]);
+    }
+
+    #[test]
+    fn test_generate_policy() {
+        let mut scope = Scope::new();
+        let name = mapping(r#"codes = { 404 = "PET_NOT_FOUND" }"#).generate_policy(
+            "pets",
+            "getPet",
+            "pets_getPet_method",
+            &mut scope,
+        );
+        let content = scope.to_string();
+
+        assert_eq!(name, "PETS_GET_PET_METHOD_ERRORS");
+        assert!(content.contains("const PETS_GET_PET_METHOD_ERRORS: ErrorPolicy"));
+        assert!(content.contains("codes: &[(404, \"PET_NOT_FOUND\")],"));
+        assert!(content.contains("pass_body: false,"));
+    }
+}
@@ -0,0 +1,247 @@
+use super::{
+    client_type, push_client_type, response_payload_line, ArgumentsMapping, ClientConfig,
+    ErrorMapping, Resilience,
+};
+use codegen::{Function, Scope, Struct};
+use convert_case::{Case, Casing};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TransportGRPC {
+    pub(super) endpoint: String,
+    /// Path of the `.proto` file describing the service, relative to where Asbru is run.
+    proto: String,
+    /// Package of the service inside the `.proto` file.
+    package: String,
+    /// Name of the gRPC service inside the package.
+    service: String,
+    method: HashMap<String, MethodGRPC>,
+}
+
+impl TransportGRPC {
+    pub fn methods(&self) -> &HashMap<String, MethodGRPC> {
+        &self.method
+    }
+
+    pub fn proto(&self) -> &str {
+        &self.proto
+    }
+
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    /// Name of the `.proto` file once copied inside the generated project.
+    pub fn proto_file_name(&self) -> String {
+        Path::new(&self.proto)
+            .file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or(&self.proto)
+            .to_string()
+    }
+
+    /// Generate the service file: the protobuf module compiled by the `build.rs`, the client
+    /// and a function for each method, called through the `resilience` of the service. RPCs are
+    /// not known to be idempotent, they are not retried.
+    /// `argument_types` are the Rust types of the arguments of each method, their types must be
+    /// imported inside the scope.
+    pub fn generate_service(
+        &self,
+        service_name: &str,
+        client: &ClientConfig,
+        resilience: &Resilience,
+        argument_types: &HashMap<String, HashMap<String, String>>,
+        scope: &mut Scope,
+    ) -> () {
+        scope.import("serde::de", "DeserializeOwned");
+        scope.import("tonic::transport", "Channel");
+        scope.import("tonic::transport", "Endpoint");
+
+        scope.raw(&format!(
+            "pub mod proto {{\n    tonic::include_proto!(\"{}\");\n}}",
+            self.package
+        ));
+        scope.raw(&format!(
+            "pub type Client = proto::{}_client::{}Client<Channel>;",
+            self.service.to_case(Case::Snake),
+            self.service
+        ));
+
+        push_client_type(scope, service_name);
+
+        let mut client_function = Function::new(&format!("{}_client", service_name));
+        client_function
+            .vis("pub")
+            .doc(&format!(
+                "Build the client of the `{}` service, once at startup. The connection is made on the first call.",
+                service_name
+            ))
+            .ret(format!("anyhow::Result<{}>", client_type(service_name)))
+            .line(format!(
+                "Ok({}(Client::new({}.connect_lazy())))",
+                client_type(service_name),
+                client.grpc_endpoint(&self.endpoint)
+            ));
+        scope.push_fn(client_function);
+
+        let no_types = HashMap::new();
+        for (method_name, method) in self.methods().iter() {
+            let function_name = format!("{}_{}_method", service_name, method_name);
+            let errors = ErrorMapping::default().generate_policy(
+                service_name,
+                method_name,
+                &function_name,
+                scope,
+            );
+            let resilience = resilience.generate_policy(&function_name, false, scope);
+            method.generate_method(
+                scope,
+                &function_name,
+                &errors,
+                &resilience,
+                argument_types.get(method_name).unwrap_or(&no_types),
+            )
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MethodGRPC {
+    /// Name of the RPC inside the gRPC service.
+    rpc: String,
+    /// Request message of the RPC.
+    request: String,
+    /// Fields of the request message filled from the GQL query (or mapped over).
+    request_args: Option<Vec<String>>,
+    /// Mapping of the request args to their source, when they don't match the GQL query.
+    args: Option<serde_json::Value>,
+    /// Path of the payload inside the response message, to unwrap it from its envelope.
+    response_path: Option<String>,
+}
+
+impl MethodGRPC {
+    /// Every argument needed to call this method.
+    pub fn argument_names(&self) -> Vec<String> {
+        self.request_args.iter().flatten().cloned().collect()
+    }
+
+    /// Mapping of the arguments declared inside the config.
+    pub fn arguments_mapping(&self) -> ArgumentsMapping {
+        self.args
+            .as_ref()
+            .map(ArgumentsMapping::from_json)
+            .unwrap_or_default()
+    }
+
+    pub fn request_method_construct(&self, function_name: &str) -> String {
+        let args = self
+            .argument_names()
+            .iter()
+            .map(|x| format!("{key}: {key}.into()", key = x.to_case(Case::Snake)))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        format!(
+            "{}MethodRequestArgs {{ {} }}",
+            function_name.to_case(Case::Pascal),
+            args
+        )
+    }
+
+    /// Generate method service code function
+    /// The request arguments are converted into the protobuf request message, and the protobuf
+    /// response message is converted into the domain type, both through their serde
+    /// representation.
+    ///
+    /// Request arguments are typed with `argument_types`, and are `String` by default. Error
+    /// statuses are mapped into a `ServiceError` by the `errors` policy, and each attempt is made
+    /// through the `resilience` policy.
+    pub fn generate_method(
+        &self,
+        scope: &mut Scope,
+        function_name: &str,
+        errors: &str,
+        resilience: &str,
+        argument_types: &HashMap<String, String>,
+    ) -> () {
+        let mut function = Function::new(&function_name.to_case(Case::Snake));
+        let attempt_function_name = format!("{}_attempt", function_name.to_case(Case::Snake));
+        let mut attempt_function = Function::new(&attempt_function_name);
+        let request_args_struct_name =
+            format!("{}RequestArgs", function_name.to_case(Case::Pascal));
+
+        scope.import("serde", "Serialize");
+        scope.import("serde", "Deserialize");
+
+        let mut request_args_struct = Struct::new(&request_args_struct_name);
+        request_args_struct
+            .vis("pub")
+            .derive("Serialize")
+            .derive("Deserialize")
+            .derive("Clone");
+
+        for argument in self.argument_names() {
+            let name = argument.to_case(Case::Snake);
+            let field_type = argument_types
+                .get(&name)
+                .map(|ty| ty.as_str())
+                .unwrap_or("String");
+
+            request_args_struct.field(&format!("pub {}", name), field_type);
+        }
+
+        attempt_function
+            .set_async(true)
+            .generic("T: DeserializeOwned")
+            .arg("client", "&Client")
+            .arg("request", &format!("&{}", request_args_struct_name))
+            .ret("Result<T, ServiceError>")
+            .line(format!(
+                r#"
+        let message = serde_json::to_value(request)
+            .and_then(serde_json::from_value::<proto::{request}>)
+            .map_err(|e| {errors}.bad_request(e))?;
+        let response = client
+            .clone()
+            .{rpc}(tonic::Request::new(message))
+            .await
+            .map_err(|e| {errors}.grpc(e.code() as i32, e.message()))?
+            .into_inner();
+
+        let response = serde_json::to_value(&response).map_err(|e| {errors}.bad_response(e))?;
+        {payload}
+
+        serde_json::from_value(response).map_err(|e| {errors}.bad_response(e))
+            "#,
+                request = self.request,
+                errors = errors,
+                rpc = self.rpc.to_case(Case::Snake),
+                payload = response_payload_line(self.response_path.as_deref(), "response")
+            ));
+
+        function
+            .set_async(true)
+            .vis("pub")
+            .generic("T: DeserializeOwned")
+            .arg("client", "&Client")
+            .arg("request", &request_args_struct_name)
+            .ret("Result<T, ServiceError>")
+            .line(format!(
+                r#"
+        {resilience}
+            .call(&{errors}, || {attempt}::<T>(client, &request))
+            .await
+            "#,
+                resilience = resilience,
+                errors = errors,
+                attempt = attempt_function_name
+            ));
+
+        scope
+            .push_struct(request_args_struct)
+            .push_fn(attempt_function)
+            .push_fn(function);
+    }
+}
@@ -0,0 +1,115 @@
+use codegen::{Function, Scope};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Headers sent to a HTTP service on top of the `headers` of its client.
+///
+/// ```toml
+/// [services.pets.transport.info.headers]
+/// forward = ["authorization", "x-tenant-id"]
+/// env = { "x-api-key" = "PETS_API_KEY" }
+/// args = { "x-owner-id" = "ownerId" }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct HeaderPolicy {
+    /// Headers of the incoming GraphQL request forwarded as they are.
+    pub forward: Vec<String>,
+    /// Headers read from environment variables when the client is built, by header name.
+    pub env: BTreeMap<String, String>,
+    /// Headers computed from the GraphQL arguments of the field, by header name.
+    pub args: BTreeMap<String, String>,
+}
+
+impl HeaderPolicy {
+    /// Generate the `{service}_headers` function, selecting the forwarded incoming headers.
+    pub fn generate_headers_function(&self, service_name: &str, scope: &mut Scope) {
+        scope.import("crate::infrastructure::headers", "ForwardedHeaders");
+        scope.import("crate::infrastructure::headers", "IncomingHeaders");
+
+        let names = self
+            .forward
+            .iter()
+            .map(|x| format!("{:?}", x.to_lowercase()))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let mut function = Function::new(&format!("{}_headers", service_name));
+        function
+            .vis("pub")
+            .doc(&format!(
+                "Headers of the incoming request forwarded to the `{}` service.",
+                service_name
+            ))
+            .arg("incoming", "Option<&IncomingHeaders>")
+            .ret("ForwardedHeaders")
+            .line(format!(
+                "incoming.map(|x| x.forwarded(&[{}])).unwrap_or_default()",
+                names
+            ));
+        scope.push_fn(function);
+    }
+}
+
+/// Generate the `infrastructure/headers.rs` module: the incoming headers, stored inside the
+/// request data by the server, and the headers sent with a call to a service.
+pub fn generate_headers_module() -> String {
+    let mut scope = Scope::new();
+    scope.import("std::collections", "HashMap");
+    scope.raw(
+        r#"/// Headers of the incoming GraphQL request, stored inside the request data by the server.
+/// Names are lowercase.
+#[derive(Clone, Debug, Default)]
+pub struct IncomingHeaders(pub HashMap<String, String>);
+
+impl IncomingHeaders {
+    /// Headers which are not valid UTF-8 are dropped.
+    pub fn new<'a>(headers: impl Iterator<Item = (&'a str, &'a [u8])>) -> Self {
+        IncomingHeaders(
+            headers
+                .filter_map(|(name, value)| {
+                    std::str::from_utf8(value)
+                        .ok()
+                        .map(|value| (name.to_lowercase(), value.to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Add the headers sent inside the payload of the `connection_init` message of a websocket,
+    /// browsers can't set the headers of the upgrade request. Only string values are headers,
+    /// they take precedence over the headers of the upgrade request.
+    pub fn with_payload(mut self, payload: &serde_json::Value) -> Self {
+        if let Some(payload) = payload.as_object() {
+            self.0.extend(payload.iter().filter_map(|(name, value)| {
+                value.as_str().map(|value| (name.to_lowercase(), value.to_string()))
+            }));
+        }
+        self
+    }
+
+    /// The listed headers, to forward them to a service.
+    pub fn forwarded(&self, names: &[&str]) -> ForwardedHeaders {
+        ForwardedHeaders(
+            names
+                .iter()
+                .filter_map(|name| self.0.get(*name).map(|value| (name.to_string(), value.clone())))
+                .collect(),
+        )
+    }
+}
+
+/// Headers sent with a call to a service. They are part of the DataLoader keys so nodes loaded
+/// for a caller are not shared with another one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ForwardedHeaders(pub Vec<(String, String)>);
+
+impl ForwardedHeaders {
+    pub fn insert<V: ToString>(&mut self, name: &str, value: V) {
+        self.0.push((name.to_string(), value.to_string()));
+    }
+}"#,
+    );
+
+    scope.to_string()
+}
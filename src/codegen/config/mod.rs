@@ -6,14 +6,101 @@ use regex::Regex;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+mod args;
+mod auth;
+mod client;
+mod errors;
+mod grpc;
+mod headers;
+mod package;
+mod resilience;
+mod scalars;
+mod server;
+pub use args::{ArgumentSource, ArgumentsMapping};
+pub use auth::{Auth, AuthKey};
+pub use client::{client_type, push_client_type, ClientConfig};
+pub use errors::{generate_errors_module, ErrorMapping};
+pub use grpc::{MethodGRPC, TransportGRPC};
+pub use headers::{generate_headers_module, HeaderPolicy};
+pub use package::Package;
+pub use resilience::{generate_resilience_module, Resilience};
+pub use scalars::{preset_type, ScalarConfig, ScalarDependency};
+pub use server::{Framework, Server};
+
 lazy_static! {
     static ref RE_ARGS: Regex = Regex::new(r#"\{(.*?)\}"#).unwrap();
 }
 
+/// Types which can be formatted inside a route.
+const ROUTE_TYPES: [&str; 5] = ["String", "i32", "i64", "f64", "bool"];
+
+/// Arguments of a method backing a Connection filled with the Relay pagination arguments.
+pub const PAGINATION_ARGUMENTS: [&str; 4] = ["after", "before", "first", "last"];
+
+/// How the only argument of a method backing a node receives the ids of a batch.
+#[derive(Debug, PartialEq)]
+pub enum BatchArgument {
+    /// The list of ids, inside the body of a HTTP request or inside a gRPC message.
+    List(String),
+    /// The ids joined with commas, inside the query string.
+    Joined(String),
+}
+
+/// Name of the struct field generated for an argument, the argument name is kept when
+/// serialized and `None` values are skipped when `skip_none`.
+fn argument_field_name(argument: &str, skip_none: bool) -> String {
+    let name = argument.to_case(Case::Snake);
+    let mut attributes = Vec::new();
+
+    if name != *argument {
+        attributes.push(format!("rename = \"{}\"", argument));
+    }
+    if skip_none {
+        attributes.push("skip_serializing_if = \"Option::is_none\"".to_string());
+    }
+
+    if attributes.is_empty() {
+        format!("pub {}", name)
+    } else {
+        format!("#[serde({})]\npub {}", attributes.join(", "), name)
+    }
+}
+
+/// JSON pointer of a path inside a JSON document, the path is either a JSON pointer already
+/// (`/data/items`) or dot separated (`data.items`).
+pub fn json_pointer(path: &str) -> String {
+    if path.is_empty() || path.starts_with('/') {
+        return path.to_string();
+    }
+
+    path.split('.')
+        .map(|x| format!("/{}", x.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Line which replaces the `serde_json::Value` inside `variable` by the payload at
+/// `response_path`, a missing payload is `null`.
+fn response_payload_line(response_path: Option<&str>, variable: &str) -> String {
+    match response_path {
+        Some(path) => format!(
+            "let {variable} = {variable}.pointer(\"{pointer}\").cloned().unwrap_or(serde_json::Value::Null);",
+            variable = variable,
+            pointer = json_pointer(path)
+        ),
+        None => "".to_string(),
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct TransportHTTP {
     endpoint: String,
     method: HashMap<String, MethodHTTP>,
+    /// Headers forwarded from the incoming request or computed for each call.
+    #[serde(default)]
+    headers: HeaderPolicy,
+    /// Mapping of the errors of every method into GraphQL errors.
+    #[serde(default)]
+    errors: ErrorMapping,
     // There is multiple possible call:
     // GET api/get/{id}/{param}
     //
@@ -28,12 +115,141 @@ impl TransportHTTP {
     pub fn methods(&self) -> &HashMap<String, MethodHTTP> {
         &self.method
     }
+
+    pub fn headers(&self) -> &HeaderPolicy {
+        &self.headers
+    }
+
+    /// Mapping of the errors of every method.
+    pub fn errors(&self) -> &ErrorMapping {
+        &self.errors
+    }
+
+    /// Mapping of the errors of a method, on top of the mapping of the service.
+    pub fn error_mapping(&self, method_name: &str) -> ErrorMapping {
+        self.errors
+            .merge(self.method.get(method_name).and_then(|x| x.errors.as_ref()))
+    }
+
+    /// Generate the service file: the client, the forwarded headers and a function for each
+    /// method, called through the `resilience` of the service and of the method.
+    /// `argument_types` are the Rust types of the arguments of each method, their types must be
+    /// imported inside the scope.
+    pub fn generate_service(
+        &self,
+        service_name: &str,
+        client: &ClientConfig,
+        resilience: &Resilience,
+        argument_types: &HashMap<String, HashMap<String, String>>,
+        scope: &mut Scope,
+    ) -> () {
+        scope.import("reqwest", "Client");
+        scope.import("reqwest", "RequestBuilder");
+        scope.import("serde::de", "DeserializeOwned");
+
+        if self.methods().values().any(|method| method.is_streamable()) {
+            scope.import("futures", "Stream");
+        }
+
+        // Route arguments are percent-encoded as path segments.
+        if self
+            .methods()
+            .values()
+            .any(|method| method.has_route_arguments())
+        {
+            scope.import("percent_encoding", "utf8_percent_encode");
+            scope.import("percent_encoding", "AsciiSet");
+            scope.import("percent_encoding", "CONTROLS");
+            scope.raw(
+                r#"/// Characters percent-encoded inside a path segment.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');"#,
+            );
+        }
+
+        push_client_type(scope, service_name);
+
+        let mut client_function = Function::new(&format!("{}_client", service_name));
+        client_function
+            .vis("pub")
+            .doc(&format!(
+                "Build the client of the `{}` service, once at startup.",
+                service_name
+            ))
+            .ret(format!("anyhow::Result<{}>", client_type(service_name)))
+            .line(client.http_client_lines(&self.headers.env))
+            .line(format!("Ok({}(client))", client_type(service_name)));
+        scope.push_fn(client_function);
+        self.headers.generate_headers_function(service_name, scope);
+
+        let no_types = HashMap::new();
+        for (method_name, method) in self.methods().iter() {
+            let function_name = format!("{}_{}_method", service_name, method_name);
+            let errors = self.error_mapping(method_name).generate_policy(
+                service_name,
+                method_name,
+                &function_name,
+                scope,
+            );
+            let resilience = resilience.merge(method.resilience()).generate_policy(
+                &function_name,
+                method.is_idempotent(),
+                scope,
+            );
+            method.generate_method(
+                scope,
+                &self.endpoint,
+                &function_name,
+                &errors,
+                &resilience,
+                argument_types.get(method_name).unwrap_or(&no_types),
+            )
+        }
+    }
 }
 
 impl MethodHTTP {
+    /// Every argument needed to call this method, from the route, the body and the query.
+    pub fn argument_names(&self) -> Vec<String> {
+        RE_ARGS
+            .captures_iter(&self.route)
+            .map(|x| x[1].to_string())
+            .chain(self.body_args.iter().flatten().cloned())
+            .chain(self.query_args.iter().flatten().cloned())
+            .collect()
+    }
+
+    /// Tell if the route has placeholders.
+    pub fn has_route_arguments(&self) -> bool {
+        RE_ARGS.is_match(&self.route)
+    }
+
+    /// Tell if the argument is a placeholder of the route.
+    pub fn is_route_argument(&self, name: &str) -> bool {
+        RE_ARGS.captures_iter(&self.route).any(|x| &x[1] == name)
+    }
+
     pub fn query_method_construct(&self, function_name: &str) -> String {
         let mut args = "".to_string();
 
+        if let Some(query_args) = &self.query_args {
+            args = query_args
+                .iter()
+                .map(|x| format!("{key}: {key}.into()", key = x.to_case(Case::Snake)))
+                .collect::<Vec<String>>()
+                .join(", ");
+        }
+
         format!(
             "{}MethodQueryArgs {{ {} }}",
             function_name.to_case(Case::Pascal),
@@ -78,12 +294,80 @@ impl MethodHTTP {
         )
     }
 
+    /// GraphQL types of the arguments declared inside the config.
+    pub fn declared_types(&self) -> Vec<(&String, &String)> {
+        self.types.iter().flatten().collect()
+    }
+
+    /// Mapping of the arguments declared inside the config.
+    pub fn arguments_mapping(&self) -> ArgumentsMapping {
+        self.args
+            .as_ref()
+            .map(ArgumentsMapping::from_json)
+            .unwrap_or_default()
+    }
+
+    /// Tell if the argument is sent inside the query string.
+    pub fn is_query_argument(&self, name: &str) -> bool {
+        self.query_args.iter().flatten().any(|x| x == name)
+    }
+
+    /// How the ids of a batch of nodes are sent to this method, or why they can't be: a route
+    /// placeholder holds only one id, and a body is only sent with `POST`, `PUT` and `PATCH`.
+    pub fn batch_argument(&self) -> Result<BatchArgument, String> {
+        let argument = match self.argument_names().as_slice() {
+            [argument] => argument.to_owned(),
+            _ => return Err("it must have only one argument".to_string()),
+        };
+
+        if self.is_route_argument(&argument) {
+            return Err(format!("its argument {} is inside the route", argument));
+        }
+        if self.is_query_argument(&argument) {
+            return Ok(BatchArgument::Joined(argument));
+        }
+        if !self.http_method.has_body() {
+            return Err(format!(
+                "its argument {} is inside the body of a {} request, which is not sent",
+                argument,
+                self.http_method.name()
+            ));
+        }
+
+        match self.types.as_ref().and_then(|x| x.get(&argument)) {
+            Some(declared) if !declared.starts_with('[') => Err(format!(
+                "its argument {} is declared as {}, not as a list",
+                argument, declared
+            )),
+            _ => Ok(BatchArgument::List(argument)),
+        }
+    }
+
     /// Generate method service code function
     /// We compute the necessary arguments while creating the Function code, then we create a
     /// public struct which will describe the request Arguments and which will be used inside the
     /// application/*.rs code for queries.
-    pub fn generate_method(&self, scope: &mut Scope, endpoint: &str, function_name: &str) -> () {
+    ///
+    /// Arguments are typed with `argument_types`, the Rust types of the arguments by snake case
+    /// name, and are `String` by default. Query arguments are optional by default and `None`
+    /// values are not sent, like missing pagination arguments inside the body. Body and query
+    /// arguments keep their names once serialized.
+    ///
+    /// The `headers` of a call are sent on top of the default headers of the client. Failures
+    /// are mapped into a `ServiceError` by the `errors` policy, and each attempt is made through
+    /// the `resilience` policy.
+    pub fn generate_method(
+        &self,
+        scope: &mut Scope,
+        endpoint: &str,
+        function_name: &str,
+        errors: &str,
+        resilience: &str,
+        argument_types: &HashMap<String, String>,
+    ) -> () {
         let mut function = Function::new(&function_name.to_case(Case::Snake));
+        let attempt_function_name = format!("{}_attempt", function_name.to_case(Case::Snake));
+        let mut attempt_function = Function::new(&attempt_function_name);
         let body_args_struct_name = format!("{}BodyArgs", function_name.to_case(Case::Pascal));
         let query_args_struct_name = format!("{}QueryArgs", function_name.to_case(Case::Pascal));
         let route_args_struct_name = format!("{}RouteArgs", function_name.to_case(Case::Pascal));
@@ -95,38 +379,63 @@ impl MethodHTTP {
         body_args_struct
             .vis("pub")
             .derive("Serialize")
-            .derive("Deserialize");
+            .derive("Deserialize")
+            .derive("Clone");
 
         let mut query_args_struct = Struct::new(&query_args_struct_name);
         query_args_struct
             .vis("pub")
             .derive("Serialize")
-            .derive("Deserialize");
+            .derive("Deserialize")
+            .derive("Clone");
 
         let mut route_args_struct = Struct::new(&route_args_struct_name);
         route_args_struct
             .vis("pub")
             .derive("Serialize")
-            .derive("Deserialize");
+            .derive("Deserialize")
+            .derive("Clone");
 
-        if let Some(body_args) = &self.body_args {
-            let _fields = body_args
-                .iter()
-                .map(|x| {
-                    body_args_struct.field(&format!("pub {}", x.to_case(Case::Snake)), "String");
-                })
-                .collect::<Vec<()>>();
+        for argument in self.body_args.iter().flatten() {
+            let field_type = argument_types
+                .get(&argument.to_case(Case::Snake))
+                .map(|ty| ty.as_str())
+                .unwrap_or("String");
+
+            // Missing pagination arguments are not sent.
+            let optional = PAGINATION_ARGUMENTS.contains(&argument.as_str())
+                && field_type.starts_with("Option<");
+
+            body_args_struct.field(&argument_field_name(argument, optional), field_type);
+        }
+
+        for argument in self.query_args.iter().flatten() {
+            let field_type = argument_types
+                .get(&argument.to_case(Case::Snake))
+                .cloned()
+                .unwrap_or_else(|| "Option<String>".to_string());
+            let optional = field_type.starts_with("Option<");
+
+            query_args_struct.field(&argument_field_name(argument, optional), &field_type);
         }
 
         let format_url = RE_ARGS
             .captures_iter(&self.route)
             .map(|x| {
+                // Route arguments are formatted inside the URL, only scalars can be.
+                let field_type = argument_types
+                    .get(&x[1].to_case(Case::Snake))
+                    .map(|ty| ty.as_str())
+                    .filter(|ty| ROUTE_TYPES.contains(ty))
+                    .unwrap_or("String");
+
                 route_args_struct.field(
                     &format!("pub {}", x[1].to_string().to_case(Case::Snake)),
-                    "String",
+                    field_type,
                 );
                 format!(
-                    ", {key} = route.{key}",
+                    ", {placeholder} = utf8_percent_encode(&route.{key}.to_string(), PATH_SEGMENT)",
+                    placeholder = &x[1],
                     key = x[1].to_string().to_case(Case::Snake)
                 )
             })
@@ -135,27 +444,83 @@ impl MethodHTTP {
 
         let final_endpoint = format!("{}{}", endpoint, self.route);
 
-        let client_method_codegen_line = match self.http_method {
-            HTTPMethod::GET => format!(
-                ".get(format!(\"{url}\"{format}))",
-                url = final_endpoint,
-                format = format_url
-            ),
-            HTTPMethod::POST => format!(".post(\"{url}\")", url = final_endpoint),
-            _ => unreachable!(),
+        let client_method_codegen_line = format!(
+            ".request(reqwest::Method::{method}, format!(\"{url}\"{format}))",
+            method = self.http_method.name(),
+            url = final_endpoint,
+            format = format_url
+        );
+
+        // Only verbs with a body semantic send the body.
+        let (body_arg, body_codegen_line) = if self.http_method.has_body() {
+            ("body", "\n            .json(body)")
+        } else {
+            ("_body", "")
         };
 
-        /*
-        let client_method_post_body = match method {
-            "POST" => format!(
+        let request_function_name = format!("{}_request", function_name.to_case(Case::Snake));
+        let mut request_function = Function::new(&request_function_name);
+        request_function
+            .vis("pub")
+            .arg("client", "&Client")
+            .arg(body_arg, &format!("&{}", body_args_struct_name))
+            .arg("query", &format!("&{}", query_args_struct_name))
+            .arg("route", &format!("&{}", route_args_struct_name))
+            .arg("headers", "&ForwardedHeaders")
+            .ret("RequestBuilder")
+            .line(format!(
                 r#"
-            .body(serde_json::json!({
-            }))
-            "#
-            ),
-            _ => "",
-        };
-        */
+        let mut request = client{endpoint}{body}
+            .query(query);
+        for (name, value) in headers.0.iter() {{
+            request = request.header(name.as_str(), value.as_str());
+        }}
+
+        request
+            "#,
+                endpoint = client_method_codegen_line,
+                body = body_codegen_line
+            ));
+
+        attempt_function
+            .set_async(true)
+            .generic("T: DeserializeOwned")
+            .arg("client", "&Client")
+            .arg("body", &format!("&{}", body_args_struct_name))
+            .arg("query", &format!("&{}", query_args_struct_name))
+            .arg("route", &format!("&{}", route_args_struct_name))
+            .arg("headers", "&ForwardedHeaders")
+            .ret("Result<T, ServiceError>")
+            .line(format!(
+                r#"
+        let response = {request}(client, body, query, route, headers)
+            .send()
+            .await
+            .map_err(|e| {errors}.transport(e.is_timeout(), e))?;
+        let status = response.status();
+        let response = response
+            .text()
+            .await
+            .map_err(|e| {errors}.transport(e.is_timeout(), e))?;
+        if !status.is_success() {{
+            return Err({errors}.status(status.as_u16(), &response));
+        }}
+
+        // HEAD requests and empty responses are deserialized from `null`.
+        let response = if response.is_empty() {{
+            serde_json::Value::Null
+        }} else {{
+            serde_json::from_str::<serde_json::Value>(&response)
+                .map_err(|e| {errors}.bad_response(e))?
+        }};
+        {payload}
+
+        serde_json::from_value::<T>(response).map_err(|e| {errors}.bad_response(e))
+            "#,
+                request = request_function_name,
+                errors = errors,
+                payload = response_payload_line(self.response_path.as_deref(), "response")
+            ));
 
         function
             .set_async(true)
@@ -165,59 +530,69 @@ impl MethodHTTP {
             .arg("body", &body_args_struct_name)
             .arg("query", &query_args_struct_name)
             .arg("route", &route_args_struct_name)
-            .ret("anyhow::Result<T>")
+            .arg("headers", "&ForwardedHeaders")
+            .ret("Result<T, ServiceError>")
             .line(format!(
                 r#"
-        let result = client{endpoint}
-            .body(serde_json::to_string(&body)?)
-            .query(&query)
-            .send()
-            .await
-            .map_err(|e| anyhow::anyhow!(e))?
-            .json::<T>()
+        {resilience}
+            .call(&{errors}, || {{
+                {attempt}::<T>(client, &body, &query, &route, headers)
+            }})
             .await
-            .map_err(|e| anyhow::anyhow!(e));
-
-        result
             "#,
-                endpoint = client_method_codegen_line
+                resilience = resilience,
+                errors = errors,
+                attempt = attempt_function_name
             ));
-        /*
-
-            let _service_fn = scope
-                .new_fn(&format!("{}_service", service_name))
-                .set_async(truekk)
-                .vis("pub")
-                .generic("T: DeserializeOwned")
-                .arg("client", "&Client")
-                .ret("anyhow::Result<T>")
-                .line(format!(
-                    r#"
-        let result = client
-            .post("{endpoint}")
-            .body(
-                serde_json::json!({{
-                    "test": "blbl",
-                }})
-                .to_string(),
-            )
-            .send()
-            .await
-            .map_err(|e| anyhow::anyhow!(e))?
-            .json::<T>()
-            .await
-            .map_err(|e| anyhow::anyhow!(e));
 
-        result
-            "#,
-                    endpoint = "https://truc.io"
-                ));
-            */
         scope
             .push_struct(body_args_struct)
             .push_struct(route_args_struct)
             .push_struct(query_args_struct)
+            .push_fn(request_function)
+            .push_fn(attempt_function)
             .push_fn(function);
+
+        if let Some(stream) = &self.stream {
+            scope.push_fn(stream.generate_stream_function(
+                function_name,
+                &body_args_struct_name,
+                &query_args_struct_name,
+                &route_args_struct_name,
+                errors,
+                self.response_path.as_deref(),
+            ));
+        }
+    }
+
+    /// Tell if this method can be used as a streaming source for subscriptions.
+    pub fn is_streamable(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Mapping of the errors, overriding the one of the service.
+    pub fn errors(&self) -> Option<&ErrorMapping> {
+        self.errors.as_ref()
+    }
+
+    /// Timeout, retries and circuit breaker, overriding the ones of the service.
+    pub fn resilience(&self) -> Option<&Resilience> {
+        self.resilience.as_ref()
+    }
+
+    /// Tell if a failed call to this method can be retried.
+    pub fn is_idempotent(&self) -> bool {
+        self.http_method.is_idempotent()
+    }
+
+    /// Name of the HTTP verb of this method.
+    pub fn http_method_name(&self) -> &'static str {
+        self.http_method.name()
+    }
+
+    /// Tell if this method is consumed as a Server-Sent Events stream.
+    pub fn is_sse(&self) -> bool {
+        matches!(self.stream, Some(StreamHTTP::SSE))
     }
 
     /// Generate an API call with a transformation function to get data from an API.
@@ -226,11 +601,6 @@ impl MethodHTTP {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct TransportGRPC {
-    endpoint: String,
-}
-
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(tag = "type", content = "info")]
 pub enum Transport {
@@ -243,24 +613,180 @@ pub enum HTTPMethod {
     GET,
     POST,
     PUT,
-    UPDATE,
+    /// `UPDATE` is its former name.
+    #[serde(alias = "UPDATE")]
+    PATCH,
+    DELETE,
     HEAD,
-    OPTION,
+    /// `OPTION` is its former name.
+    #[serde(alias = "OPTION")]
+    OPTIONS,
+}
+
+impl HTTPMethod {
+    /// Name of the method, as `reqwest::Method` constants.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HTTPMethod::GET => "GET",
+            HTTPMethod::POST => "POST",
+            HTTPMethod::PUT => "PUT",
+            HTTPMethod::PATCH => "PATCH",
+            HTTPMethod::DELETE => "DELETE",
+            HTTPMethod::HEAD => "HEAD",
+            HTTPMethod::OPTIONS => "OPTIONS",
+        }
+    }
+
+    /// Tell if a request with this method can be retried safely.
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(self, HTTPMethod::POST | HTTPMethod::PATCH)
+    }
+
+    /// Tell if a request with this method should send a body.
+    pub fn has_body(&self) -> bool {
+        matches!(self, HTTPMethod::POST | HTTPMethod::PUT | HTTPMethod::PATCH)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct MethodHTTP {
     route: String,
-    /// Args that should go from the GQL query (or mapped over) to the body params.
     http_method: HTTPMethod,
+    /// Args that should go from the GQL query (or mapped over) to the body params.
     body_args: Option<Vec<String>>,
     /// Args that should go from the GQL query (or mapped over) to the query params.
     query_args: Option<Vec<String>>,
+    /// How this method can be consumed as a stream by subscriptions.
+    stream: Option<StreamHTTP>,
+    /// GraphQL types of the args, when they can't be inferred from the GQL query.
+    types: Option<HashMap<String, String>>,
+    /// Mapping of the args to their source, when they don't match the GQL query.
+    args: Option<serde_json::Value>,
+    /// Path of the payload inside the response, to unwrap it from its envelope.
+    response_path: Option<String>,
+    /// Mapping of the errors, overriding the one of the service.
+    errors: Option<ErrorMapping>,
+    /// Timeout, retries and circuit breaker, overriding the ones of the service.
+    resilience: Option<Resilience>,
+}
+
+/// A streaming source built over an HTTP method, used to back subscriptions.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "type")]
+pub enum StreamHTTP {
+    /// Call the method again every `interval_ms` and emit each response.
+    Polling { interval_ms: u64 },
+    /// The method is a Server-Sent Events endpoint, each `data` event is emitted.
+    SSE,
+}
+
+impl StreamHTTP {
+    /// Generate the `{function}_stream` function which wraps a method into a `Stream` of results.
+    /// Server-Sent Events are unwrapped with `response_path` and their failures mapped by the
+    /// `errors` policy, like the responses of the method.
+    pub fn generate_stream_function(
+        &self,
+        function_name: &str,
+        body_args_struct_name: &str,
+        query_args_struct_name: &str,
+        route_args_struct_name: &str,
+        errors: &str,
+        response_path: Option<&str>,
+    ) -> Function {
+        let function_name = function_name.to_case(Case::Snake);
+        let mut function = Function::new(&format!("{}_stream", function_name));
+        function
+            .vis("pub")
+            .generic("T: DeserializeOwned + Send + 'static")
+            .arg("client", "Client")
+            .arg("body", body_args_struct_name)
+            .arg("query", query_args_struct_name)
+            .arg("route", route_args_struct_name)
+            .arg("headers", "ForwardedHeaders")
+            .ret("impl Stream<Item = Result<T, ServiceError>>");
+
+        match self {
+            StreamHTTP::Polling { interval_ms } => function.line(format!(
+                r#"
+        let interval = tokio::time::interval(std::time::Duration::from_millis({interval_ms}));
+
+        futures::stream::unfold(
+            (client, body, query, route, headers, interval),
+            |(client, body, query, route, headers, mut interval)| async move {{
+                interval.tick().await;
+                let result =
+                    {function}::<T>(&client, body.clone(), query.clone(), route.clone(), &headers)
+                        .await;
+
+                Some((result, (client, body, query, route, headers, interval)))
+            }},
+        )
+            "#,
+                interval_ms = interval_ms,
+                function = function_name
+            )),
+            StreamHTTP::SSE => function.line(format!(
+                r#"
+        async_stream::try_stream! {{
+            let response = {function}_request(&client, &body, &query, &route, &headers)
+                .header("Accept", "text/event-stream")
+                .send()
+                .await
+                .map_err(|e| {errors}.transport(e.is_timeout(), e))?;
+            let mut response = match response.status() {{
+                status if status.is_success() => response,
+                status => {{
+                    let body = response.text().await.unwrap_or_default();
+                    Err::<reqwest::Response, _>({errors}.status(status.as_u16(), &body))?
+                }}
+            }};
+            let mut buffer = String::new();
+
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| {errors}.transport(e.is_timeout(), e))?
+            {{
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+                while let Some(index) = buffer.find("\n\n") {{
+                    let event: String = buffer.drain(..index + 2).collect();
+                    let data = event
+                        .lines()
+                        .filter_map(|line| line.strip_prefix("data:"))
+                        .map(|line| line.trim_start())
+                        .collect::<Vec<&str>>()
+                        .join("\n");
+
+                    if !data.is_empty() {{
+                        let event = serde_json::from_str::<serde_json::Value>(&data)
+                            .map_err(|e| {errors}.bad_response(e))?;
+                        {payload}
+                        yield serde_json::from_value::<T>(event)
+                            .map_err(|e| {errors}.bad_response(e))?;
+                    }}
+                }}
+            }}
+        }}
+            "#,
+                function = function_name,
+                errors = errors,
+                payload = response_payload_line(response_path, "event")
+            )),
+        };
+
+        function
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Service {
     transport: Transport,
+    #[serde(default)]
+    client: ClientConfig,
+    /// Timeout, retries and circuit breaker of every method.
+    #[serde(default)]
+    resilience: Resilience,
 }
 
 impl Service {
@@ -271,17 +797,140 @@ impl Service {
         }
     }
 
-    pub fn methods(&self) -> &HashMap<String, MethodHTTP> {
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+
+    pub fn client(&self) -> &ClientConfig {
+        &self.client
+    }
+
+    pub fn resilience(&self) -> &Resilience {
+        &self.resilience
+    }
+
+    /// Headers sent to the service for each call, only HTTP services have some.
+    pub fn header_policy(&self) -> Option<&HeaderPolicy> {
         match &self.transport {
-            Transport::HTTP(http) => http.methods(),
-            Transport::GRPC(_) => todo!(),
+            Transport::HTTP(http) => Some(http.headers()),
+            Transport::GRPC(_) => None,
+        }
+    }
+
+    /// Get a method of this service if this method exist or return an Error.
+    pub fn get_a_method(&self, service_name: &str, name: &str) -> Result<Method, GenericErrors> {
+        let method = match &self.transport {
+            Transport::HTTP(http) => http.methods().get(name).map(Method::HTTP),
+            Transport::GRPC(grpc) => grpc.methods().get(name).map(Method::GRPC),
+        };
+
+        method.ok_or_else(|| {
+            GenericErrors::MethodNotFoundError(service_name.to_string(), name.to_string())
+        })
+    }
+}
+
+/// A method of a service, whatever its transport.
+pub enum Method<'a> {
+    HTTP(&'a MethodHTTP),
+    GRPC(&'a MethodGRPC),
+}
+
+impl<'a> Method<'a> {
+    /// Every argument needed to call this method.
+    pub fn argument_names(&self) -> Vec<String> {
+        match self {
+            Method::HTTP(method) => method.argument_names(),
+            Method::GRPC(method) => method.argument_names(),
+        }
+    }
+
+    /// How the ids of a batch of nodes are sent to this method, when it backs a node. The
+    /// request field of a gRPC method is a repeated field.
+    pub fn batch_argument(&self) -> Result<BatchArgument, String> {
+        match self {
+            Method::HTTP(method) => method.batch_argument(),
+            Method::GRPC(method) => match method.argument_names().as_slice() {
+                [argument] => Ok(BatchArgument::List(argument.to_owned())),
+                _ => Err("it must have only one argument".to_string()),
+            },
+        }
+    }
+
+    /// Tell if this method can be used as a streaming source for subscriptions.
+    pub fn is_streamable(&self) -> bool {
+        match self {
+            Method::HTTP(method) => method.is_streamable(),
+            Method::GRPC(_) => false,
+        }
+    }
+
+    /// GraphQL types of the arguments declared inside the config.
+    pub fn declared_types(&self) -> Vec<(&String, &String)> {
+        match self {
+            Method::HTTP(method) => method.declared_types(),
+            Method::GRPC(_) => Vec::new(),
+        }
+    }
+
+    /// Mapping of the arguments declared inside the config.
+    pub fn arguments_mapping(&self) -> ArgumentsMapping {
+        match self {
+            Method::HTTP(method) => method.arguments_mapping(),
+            Method::GRPC(method) => method.arguments_mapping(),
+        }
+    }
+
+    /// Tell if the argument is sent inside the query string, where it's optional.
+    pub fn is_query_argument(&self, name: &str) -> bool {
+        match self {
+            Method::HTTP(method) => method.is_query_argument(name),
+            Method::GRPC(_) => false,
+        }
+    }
+
+    /// Tell if the argument is a placeholder of the route, where it's required.
+    pub fn is_route_argument(&self, name: &str) -> bool {
+        match self {
+            Method::HTTP(method) => method.is_route_argument(name),
+            Method::GRPC(_) => false,
+        }
+    }
+
+    /// Arguments of the generated method function, in order, as the name of their structure
+    /// and its construct.
+    pub fn arguments_construct(&self, function_name: &str) -> Vec<(String, String)> {
+        let name =
+            |kind: &str| format!("{}Method{}Args", function_name.to_case(Case::Pascal), kind);
+
+        match self {
+            Method::HTTP(method) => vec![
+                (name("Body"), method.body_method_construct(function_name)),
+                (name("Query"), method.query_method_construct(function_name)),
+                (name("Route"), method.route_method_construct(function_name)),
+            ],
+            Method::GRPC(method) => vec![(
+                name("Request"),
+                method.request_method_construct(function_name),
+            )],
         }
     }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Config {
+    /// The generated crate.
+    #[serde(default)]
+    package: Package,
     services: HashMap<String, Service>,
+    /// The server of the generated API.
+    #[serde(default)]
+    server: Server,
+    /// Rust types of the custom scalars of the schema.
+    #[serde(default)]
+    scalars: HashMap<String, ScalarConfig>,
+    /// Authentication of the callers, required by `@scope` and `@auth`.
+    auth: Option<Auth>,
 }
 
 impl Config {
@@ -296,14 +945,158 @@ impl Config {
     pub fn services(&self) -> &HashMap<String, Service> {
         &self.services
     }
+
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+
+    pub fn server(&self) -> &Server {
+        &self.server
+    }
+
+    pub fn auth(&self) -> Option<&Auth> {
+        self.auth.as_ref()
+    }
+
+    /// Headers a browser may send to the server: `content-type`, `authorization` and the headers
+    /// forwarded to the HTTP services.
+    pub fn cors_headers(&self) -> Vec<String> {
+        let mut forwarded = self
+            .http_transports()
+            .iter()
+            .flat_map(|x| x.headers().forward.iter())
+            .map(|x| x.to_lowercase())
+            .filter(|x| x != "content-type" && x != "authorization")
+            .collect::<Vec<String>>();
+        forwarded.sort();
+        forwarded.dedup();
+
+        vec!["content-type".to_string(), "authorization".to_string()]
+            .into_iter()
+            .chain(forwarded)
+            .collect()
+    }
+
+    /// Every HTTP transport described inside the config.
+    pub fn http_transports(&self) -> Vec<&TransportHTTP> {
+        self.services
+            .values()
+            .filter_map(|service| match &service.transport {
+                Transport::HTTP(http) => Some(http),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every gRPC transport described inside the config.
+    pub fn grpc_transports(&self) -> Vec<&TransportGRPC> {
+        self.services
+            .values()
+            .filter_map(|service| match &service.transport {
+                Transport::GRPC(grpc) => Some(grpc),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Rust type of a custom scalar, declared inside the config or preset for well-known scalars.
+    pub fn scalar_type(&self, name: &str) -> Option<String> {
+        self.scalars
+            .get(name)
+            .map(|x| x.rust_type().to_string())
+            .or_else(|| preset_type(name).map(|x| x.to_string()))
+    }
+
+    /// Cargo dependency needed by the Rust type of a custom scalar.
+    pub fn scalar_dependency(&self, name: &str) -> Option<ScalarDependency> {
+        match self.scalars.get(name) {
+            Some(scalar) => scalar.dependency(),
+            None => ScalarConfig::Type(preset_type(name)?.to_string()).dependency(),
+        }
+    }
 }
 
+#[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_json_pointer() {
+        assert_eq!(json_pointer("data.items"), "/data/items");
+        assert_eq!(json_pointer("/data/items"), "/data/items");
+        assert_eq!(json_pointer("a/b.c~d"), "/a~1b/c~0d");
+        assert_eq!(json_pointer(""), "");
+    }
+
+    #[test]
+    fn test_response_payload_line() {
+        assert_eq!(response_payload_line(None, "response"), "");
+        assert_eq!(
+            response_payload_line(Some("data.items"), "response"),
+            "let response = response.pointer(\"/data/items\").cloned().unwrap_or(serde_json::Value::Null);"
+        );
+    }
+
+    #[test]
+    fn test_argument_field_name() {
+        assert_eq!(argument_field_name("id", false), "pub id");
+        assert_eq!(
+            argument_field_name("ownerId", false),
+            "#[serde(rename = \"ownerId\")]\npub owner_id"
+        );
+        assert_eq!(
+            argument_field_name("ownerId", true),
+            "#[serde(rename = \"ownerId\", skip_serializing_if = \"Option::is_none\")]\npub owner_id"
+        );
+    }
+
+    #[test]
+    fn test_http_method_aliases() {
+        let method: HTTPMethod = serde_json::from_str("\"UPDATE\"").unwrap();
+        assert_eq!(method.name(), "PATCH");
+        let method: HTTPMethod = serde_json::from_str("\"OPTION\"").unwrap();
+        assert_eq!(method.name(), "OPTIONS");
+    }
+
+    #[test]
+    fn test_batch_argument() {
+        let method = |toml_str: &str| toml::from_str::<MethodHTTP>(toml_str).unwrap();
+
+        assert_eq!(
+            method("route = \"users\"\nhttp_method = \"POST\"\nbody_args = [\"ids\"]")
+                .batch_argument(),
+            Ok(BatchArgument::List("ids".to_string()))
+        );
+        assert_eq!(
+            method("route = \"users\"\nhttp_method = \"GET\"\nquery_args = [\"ids\"]")
+                .batch_argument(),
+            Ok(BatchArgument::Joined("ids".to_string()))
+        );
+        assert!(method("route = \"users/{id}\"\nhttp_method = \"GET\"")
+            .batch_argument()
+            .is_err());
+        assert!(method(
+            "route = \"users\"\nhttp_method = \"POST\"\nbody_args = [\"ids\"]\ntypes = { ids = \"ID!\" }"
+        )
+        .batch_argument()
+        .is_err());
+    }
+
     #[test]
     fn test_config_format() {
         let toml_str = r#"
+        [package]
+        name = "user-api"
+        workspace = true
+
+        [package.dependencies]
+        tracing = "0.1"
+
+        [server]
+        framework = "axum"
+        path = "/api/graphql"
+        cors_origins = ["https://app.example.com"]
+
         [services]
 
         [services.user.transport]
@@ -312,20 +1105,70 @@ mod test {
         [services.user.transport.info]
         endpoint = "http://truc.io:9009"
 
+        [services.user.client]
+        timeout_ms = 5000
+        pool_max_idle_per_host = 32
+        headers = { "x-api-key" = "${USER_API_KEY}" }
+
+        [services.user.resilience]
+        timeout_ms = 2000
+        retries = 2
+        backoff_ms = 100
+        breaker = { failures = 5, reset_ms = 30000 }
+
+        [services.user.transport.info.headers]
+        forward = ["authorization", "x-tenant-id"]
+        env = { "x-api-key" = "USER_API_KEY" }
+        args = { "x-owner-id" = "ownerId" }
+
+        [services.user.transport.info.errors]
+        codes = { 404 = "USER_NOT_FOUND" }
+        pass_body = true
+        redact = ["password"]
+
         [services.user.transport.info.method.test]
         route = "api/v3/testMethod"
         http_method = "GET"
+        errors = { codes = { 409 = "USER_ALREADY_EXISTS" }, pass_body = false }
 
         [services.user.transport.info.method.testPost]
         route = "api/v3/testMethod"
         http_method = "POST"
         body_args = ["status"]
+        response_path = "data.items"
+        resilience = { timeout_ms = 5000, breaker = { failures = 3 } }
+
+        [services.user.transport.info.method.testStream]
+        route = "api/v3/testMethod"
+        http_method = "GET"
+        stream = { type = "Polling", interval_ms = 1000 }
+
+        [services.friends.transport]
+        type = "GRPC"
+
+        [services.friends.transport.info]
+        endpoint = "http://[::1]:50051"
+        proto = "proto/friends.proto"
+        package = "friends"
+        service = "FriendService"
+
+        [services.friends.transport.info.method.friendById]
+        rpc = "GetFriend"
+        request = "GetFriendRequest"
+        request_args = ["id"]
+
+        [auth]
+        key = { type = "Jwks", path = "keys/jwks.json" }
+        issuer = "https://auth.example.com/"
+        audience = "user-api"
+
+        [scalars]
+        Url = "url::Url"
+        Decimal = { type = "rust_decimal::Decimal", version = "1", async_graphql_feature = "decimal" }
         "#;
 
         let config: Result<Config, _> = toml::from_str(toml_str);
 
-        dbg!(&config);
-
-        assert_eq!(config.is_ok(), true);
+        assert!(config.is_ok(), "{:?}", config.err());
     }
 }
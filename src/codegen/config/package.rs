@@ -0,0 +1,46 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The generated crate, from the `[package]` section.
+///
+/// ```toml
+/// [package]
+/// name = "pets-api"
+/// authors = ["Jane Doe <jane@doe.dev>"]
+/// workspace = true
+///
+/// [package.dependencies]
+/// tracing = "0.1"
+/// ```
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Package {
+    /// Name of the crate, the name of the output directory when missing.
+    pub name: Option<String>,
+    pub version: String,
+    pub authors: Vec<String>,
+    pub edition: String,
+    pub license: Option<String>,
+    pub description: Option<String>,
+    /// Generate a library crate exposing the schema, to include inside an existing workspace,
+    /// instead of a server.
+    pub workspace: bool,
+    /// Dependencies added to the generated ones, written as inside a Cargo.toml. They take
+    /// precedence over the generated ones.
+    pub dependencies: HashMap<String, serde_json::Value>,
+}
+
+impl Default for Package {
+    fn default() -> Self {
+        Package {
+            name: None,
+            version: "0.1.0".to_string(),
+            authors: Vec::new(),
+            edition: "2018".to_string(),
+            license: None,
+            description: None,
+            workspace: false,
+            dependencies: HashMap::new(),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use codegen::Scope;
+use convert_case::{Case, Casing};
+use serde_derive::{Deserialize, Serialize};
+
+/// Resilience of the calls to a service, on the service or one of its HTTP methods: every
+/// setting of a method overrides the one of its service.
+///
+/// ```toml
+/// [services.pets.resilience]
+/// timeout_ms = 2000
+/// retries = 2
+/// backoff_ms = 100
+/// max_backoff_ms = 2000
+/// breaker = { failures = 5, reset_ms = 30000 }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Resilience {
+    /// Timeout of each attempt, reading the response included.
+    pub timeout_ms: Option<u64>,
+    /// Retries of a call failing because the service is unavailable, idempotent HTTP verbs only.
+    pub retries: Option<u32>,
+    /// Base delay of the exponential backoff between retries, with full jitter.
+    pub backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub breaker: Option<CircuitBreakerConfig>,
+}
+
+/// Circuit breaker opened after `failures` consecutive failed calls, at least one. Calls fail
+/// fast while it's open and are let through again after `reset_ms` to probe the service.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    pub failures: u32,
+    #[serde(default = "default_reset_ms")]
+    pub reset_ms: u64,
+}
+
+fn default_reset_ms() -> u64 {
+    30_000
+}
+
+impl Resilience {
+    /// The resilience of a method, on top of the resilience of its service.
+    pub fn merge(&self, method: Option<&Resilience>) -> Resilience {
+        let method = match method {
+            Some(method) => method,
+            None => return self.clone(),
+        };
+
+        Resilience {
+            timeout_ms: method.timeout_ms.or(self.timeout_ms),
+            retries: method.retries.or(self.retries),
+            backoff_ms: method.backoff_ms.or(self.backoff_ms),
+            max_backoff_ms: method.max_backoff_ms.or(self.max_backoff_ms),
+            breaker: method.breaker.clone().or_else(|| self.breaker.clone()),
+        }
+    }
+
+    /// Push the `Resilience` static of a method called by `function_name` inside the scope, and
+    /// return its name. Calls which are not `idempotent` are never retried.
+    pub fn generate_policy(
+        &self,
+        function_name: &str,
+        idempotent: bool,
+        scope: &mut Scope,
+    ) -> String {
+        scope.import("crate::infrastructure::resilience", "Resilience");
+
+        let name = format!("{}_RESILIENCE", function_name.to_case(Case::UpperSnake));
+        let timeout = match self.timeout_ms {
+            Some(timeout) => format!("Some(Duration::from_millis({}))", timeout),
+            None => "None".to_string(),
+        };
+        let retries = if idempotent {
+            self.retries.unwrap_or(0)
+        } else {
+            0
+        };
+        let breaker = match &self.breaker {
+            Some(breaker) => {
+                scope.import("crate::infrastructure::resilience", "CircuitBreaker");
+                format!(
+                    "Some(CircuitBreaker::new({}, {}))",
+                    breaker.failures, breaker.reset_ms
+                )
+            }
+            None => "None".to_string(),
+        };
+        scope.import("std::time", "Duration");
+
+        scope.raw(&format!(
+            r#"/// Timeout, retries and circuit breaker of `{function}`.
+static {name}: Resilience = Resilience {{
+    timeout: {timeout},
+    retries: {retries},
+    backoff: Duration::from_millis({backoff}),
+    max_backoff: Duration::from_millis({max_backoff}),
+    breaker: {breaker},
+}};"#,
+            function = function_name,
+            name = name,
+            timeout = timeout,
+            retries = retries,
+            backoff = self.backoff_ms.unwrap_or(100),
+            max_backoff = self.max_backoff_ms.unwrap_or(2_000),
+            breaker = breaker
+        ));
+
+        name
+    }
+}
+
+/// Generate the `infrastructure/resilience.rs` module: the timeout, retries and circuit breaker
+/// wrapping the calls to the services.
+pub fn generate_resilience_module() -> String {
+    let mut scope = Scope::new();
+    scope.import("std::future", "Future");
+    scope.import("std::sync::atomic", "AtomicU32");
+    scope.import("std::sync::atomic", "AtomicU64");
+    scope.import("std::sync::atomic", "Ordering");
+    scope.import("std::time", "Duration");
+    scope.import("crate::infrastructure::errors", "ErrorPolicy");
+    scope.import("crate::infrastructure::errors", "ServiceError");
+    scope.raw(
+        r#"/// Resilience of the calls to a method, generated from the config.
+pub struct Resilience {
+    /// Timeout of each attempt.
+    pub timeout: Option<Duration>,
+    /// Retries of a call failing because the service is unavailable.
+    pub retries: u32,
+    /// Base delay of the exponential backoff between retries.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    pub breaker: Option<CircuitBreaker>,
+}
+
+impl Resilience {
+    /// Make a call, each attempt is made by `call`. Failures mapped by `errors` are retried when
+    /// the service is unavailable. The circuit breaker is asked once for the whole call, and a
+    /// failure of the service is counted once, after the last retry.
+    pub async fn call<T, F, Fut>(
+        &self,
+        errors: &ErrorPolicy,
+        mut call: F,
+    ) -> Result<T, ServiceError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ServiceError>>,
+    {
+        if let Some(breaker) = &self.breaker {
+            if !breaker.allow() {
+                return Err(errors.circuit_open());
+            }
+        }
+
+        let mut attempt = 0;
+        let result = loop {
+            let result = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, call())
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(errors.transport(true, format!("no response after {:?}", timeout)))
+                    }),
+                None => call().await,
+            };
+
+            match result {
+                Err(e) if e.retryable && attempt < self.retries => {
+                    tokio::time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => break result,
+            }
+        };
+
+        if let Some(breaker) = &self.breaker {
+            match &result {
+                Ok(_) => breaker.success(),
+                Err(e) if e.failure => breaker.failure(),
+                Err(_) => {}
+            }
+        }
+
+        result
+    }
+
+    /// Exponential backoff with full jitter: a random delay up to `backoff * 2^attempt`.
+    fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        ceiling.mul_f64(random())
+    }
+}
+
+/// A random number between 0 and 1, from the random keys of the standard library hasher.
+fn random() -> f64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let value = RandomState::new().build_hasher().finish();
+    (value >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Circuit breaker of a method, shared by every call. It opens after `failures` consecutive
+/// failed calls: calls fail fast while it's open, and a single call is let through after `reset` to
+/// probe the service. A success closes it.
+pub struct CircuitBreaker {
+    failures: u32,
+    reset: Duration,
+    consecutive_failures: AtomicU32,
+    /// Milliseconds since the UNIX epoch when it opened, `0` while it's closed.
+    opened_at: AtomicU64,
+}
+
+impl CircuitBreaker {
+    pub const fn new(failures: u32, reset_ms: u64) -> Self {
+        CircuitBreaker {
+            failures,
+            reset: Duration::from_millis(reset_ms),
+            consecutive_failures: AtomicU32::new(0),
+            opened_at: AtomicU64::new(0),
+        }
+    }
+
+    fn allow(&self) -> bool {
+        let opened_at = self.opened_at.load(Ordering::Acquire);
+        if opened_at == 0 {
+            return true;
+        }
+
+        // Half-open: the call re-arming the breaker is the probe, the others still fail fast.
+        let now = now_ms();
+        now.saturating_sub(opened_at) >= self.reset.as_millis() as u64
+            && self
+                .opened_at
+                .compare_exchange(opened_at, now, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+    }
+
+    fn success(&self) {
+        self.consecutive_failures.store(0, Ordering::Release);
+        self.opened_at.store(0, Ordering::Release);
+    }
+
+    fn failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::AcqRel) + 1;
+        if failures >= self.failures {
+            self.opened_at.store(now_ms(), Ordering::Release);
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_millis() as u64)
+        .unwrap_or(1)
+}"#,
+    );
+
+    scope.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resilience(toml_str: &str) -> Resilience {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let service = resilience(
+            r#"
+            timeout_ms = 2000
+            retries = 2
+            breaker = { failures = 5 }
+            "#,
+        );
+        let method = resilience("timeout_ms = 5000");
+        let merged = service.merge(Some(&method));
+
+        assert_eq!(merged.timeout_ms, Some(5000));
+        assert_eq!(merged.retries, Some(2));
+        assert_eq!(merged.breaker.as_ref().map(|x| x.reset_ms), Some(30_000));
+    }
+
+    #[test]
+    fn test_generate_policy() {
+        let policy = resilience(
+            r#"
+            timeout_ms = 2000
+            retries = 2
+            breaker = { failures = 5, reset_ms = 1000 }
+            "#,
+        );
+
+        let mut scope = Scope::new();
+        let name = policy.generate_policy("pets_getPet_method", true, &mut scope);
+        let content = scope.to_string();
+
+        assert_eq!(name, "PETS_GET_PET_METHOD_RESILIENCE");
+        assert!(content.contains("timeout: Some(Duration::from_millis(2000)),"));
+        assert!(content.contains("retries: 2,"));
+        assert!(content.contains("breaker: Some(CircuitBreaker::new(5, 1000)),"));
+    }
+
+    #[test]
+    fn test_not_idempotent_not_retried() {
+        let mut scope = Scope::new();
+        resilience("retries = 2").generate_policy("pets_addPet_method", false, &mut scope);
+        let content = scope.to_string();
+
+        assert!(content.contains("retries: 0,"));
+        assert!(content.contains("timeout: None,"));
+        assert!(content.contains("breaker: None,"));
+    }
+}
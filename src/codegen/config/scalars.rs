@@ -0,0 +1,213 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Rust type of a custom GraphQL scalar, from the `[scalars]` section.
+///
+/// ```toml
+/// [scalars]
+/// Url = "url::Url"
+/// Decimal = { type = "rust_decimal::Decimal", version = "1", features = ["serde-str"], async_graphql_feature = "decimal" }
+/// ```
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+pub enum ScalarConfig {
+    Type(String),
+    Detailed {
+        #[serde(rename = "type")]
+        rust_type: String,
+        /// Version of the crate providing the type, the crate is the first segment of its path.
+        version: Option<String>,
+        features: Option<Vec<String>>,
+        /// Feature of `async-graphql` implementing the scalar for this type.
+        async_graphql_feature: Option<String>,
+    },
+}
+
+impl ScalarConfig {
+    pub fn rust_type(&self) -> &str {
+        match self {
+            ScalarConfig::Type(rust_type) => rust_type,
+            ScalarConfig::Detailed { rust_type, .. } => rust_type,
+        }
+    }
+
+    /// Dependency needed by the Rust type, presets are used for well-known types.
+    pub fn dependency(&self) -> Option<ScalarDependency> {
+        match self {
+            ScalarConfig::Type(rust_type) => preset_dependency(rust_type),
+            ScalarConfig::Detailed {
+                rust_type,
+                version,
+                features,
+                async_graphql_feature,
+            } => {
+                let preset = preset_dependency(rust_type);
+                let name = crate_name(rust_type)?.to_string();
+                let version = match (version, features) {
+                    (Some(version), Some(features)) => {
+                        json!({ "version": version, "features": features })
+                    }
+                    (Some(version), None) => json!(version),
+                    // The features replace the ones of the preset, with its version.
+                    (None, Some(features)) => {
+                        let version = match preset.as_ref().map(|x| &x.version)? {
+                            serde_json::Value::Object(preset) => preset.get("version")?.clone(),
+                            version => version.clone(),
+                        };
+                        json!({ "version": version, "features": features })
+                    }
+                    (None, None) => preset.as_ref().map(|x| x.version.clone())?,
+                };
+
+                Some(ScalarDependency {
+                    name,
+                    version,
+                    async_graphql_feature: async_graphql_feature
+                        .clone()
+                        .or_else(|| preset.and_then(|x| x.async_graphql_feature)),
+                })
+            }
+        }
+    }
+}
+
+/// A Cargo dependency needed by the Rust type of a scalar.
+#[derive(Debug, Clone)]
+pub struct ScalarDependency {
+    /// Name of the crate.
+    pub name: String,
+    /// Version, or detailed dependency, as written inside the Cargo.toml.
+    pub version: serde_json::Value,
+    /// Feature of `async-graphql` implementing the scalar for this type.
+    pub async_graphql_feature: Option<String>,
+}
+
+/// First segment of a type path, when the type comes from another crate.
+fn crate_name(rust_type: &str) -> Option<&str> {
+    let mut segments = rust_type.split("::");
+    let name = segments.next()?;
+    segments.next()?;
+
+    match name {
+        "std" | "core" | "alloc" | "crate" | "async_graphql" | "serde_json" => None,
+        name => Some(name),
+    }
+}
+
+/// Rust types of well-known scalar names, they are implemented by `async-graphql`.
+pub fn preset_type(scalar: &str) -> Option<&'static str> {
+    match scalar {
+        "DateTime" => Some("chrono::DateTime<chrono::Utc>"),
+        "Date" => Some("chrono::NaiveDate"),
+        "Time" => Some("chrono::NaiveTime"),
+        "Url" | "URL" => Some("url::Url"),
+        "UUID" | "Uuid" => Some("uuid::Uuid"),
+        "JSON" | "Json" => Some("async_graphql::Json<serde_json::Value>"),
+        _ => None,
+    }
+}
+
+/// Dependency of a well-known Rust type, with the `async-graphql` feature implementing the
+/// scalar for it.
+fn preset_dependency(rust_type: &str) -> Option<ScalarDependency> {
+    let (name, version, features, feature) = match crate_name(rust_type)? {
+        "chrono" => ("chrono", "0.4.*", vec!["serde"], "chrono"),
+        "chrono_tz" => ("chrono-tz", "0.5.*", vec![], "chrono-tz"),
+        "url" => ("url", "2.*", vec!["serde"], "url"),
+        "uuid" => ("uuid", "0.8.*", vec!["serde"], "uuid"),
+        "bson" => ("bson", "2.*", vec![], "bson"),
+        "rust_decimal" => ("rust_decimal", "1.*", vec!["serde"], "decimal"),
+        "bigdecimal" => ("bigdecimal", "0.3.*", vec!["serde"], "bigdecimal"),
+        _ => return None,
+    };
+
+    Some(ScalarDependency {
+        name: name.to_string(),
+        version: if features.is_empty() {
+            json!(version)
+        } else {
+            json!({ "version": version, "features": features })
+        },
+        async_graphql_feature: Some(feature.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_preset_dependency() {
+        let scalar = ScalarConfig::Type("url::Url".to_string());
+        let dependency = scalar.dependency().unwrap();
+
+        assert_eq!(dependency.name, "url");
+        assert_eq!(
+            dependency.version,
+            json!({ "version": "2.*", "features": ["serde"] })
+        );
+        assert_eq!(dependency.async_graphql_feature, Some("url".to_string()));
+    }
+
+    #[test]
+    fn test_detailed_dependency() {
+        let scalar: ScalarConfig = toml::from_str::<std::collections::HashMap<String, ScalarConfig>>(
+            r#"Decimal = { type = "rust_decimal::Decimal", version = "1", features = ["serde-str"] }"#,
+        )
+        .unwrap()
+        .remove("Decimal")
+        .unwrap();
+        let dependency = scalar.dependency().unwrap();
+
+        assert_eq!(scalar.rust_type(), "rust_decimal::Decimal");
+        assert_eq!(dependency.name, "rust_decimal");
+        assert_eq!(
+            dependency.version,
+            json!({ "version": "1", "features": ["serde-str"] })
+        );
+        assert_eq!(
+            dependency.async_graphql_feature,
+            Some("decimal".to_string())
+        );
+    }
+
+    #[test]
+    fn test_preset_version_with_features() {
+        let scalar: ScalarConfig =
+            toml::from_str::<std::collections::HashMap<String, ScalarConfig>>(
+                r#"Uuid = { type = "uuid::Uuid", features = ["serde", "v4"] }"#,
+            )
+            .unwrap()
+            .remove("Uuid")
+            .unwrap();
+        let dependency = scalar.dependency().unwrap();
+
+        assert_eq!(
+            dependency.version,
+            json!({ "version": "0.8.*", "features": ["serde", "v4"] })
+        );
+        assert_eq!(dependency.async_graphql_feature, Some("uuid".to_string()));
+    }
+
+    #[test]
+    fn test_no_dependency() {
+        assert!(ScalarConfig::Type("String".to_string())
+            .dependency()
+            .is_none());
+        assert!(ScalarConfig::Type("crate::scalars::Email".to_string())
+            .dependency()
+            .is_none());
+        assert!(ScalarConfig::Type("serde_json::Value".to_string())
+            .dependency()
+            .is_none());
+    }
+
+    #[test]
+    fn test_preset_type() {
+        assert_eq!(
+            preset_type("DateTime"),
+            Some("chrono::DateTime<chrono::Utc>")
+        );
+        assert_eq!(preset_type("Email"), None);
+    }
+}
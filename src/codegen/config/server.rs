@@ -0,0 +1,68 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// HTTP framework serving the generated API, with its `async-graphql` integration.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Framework {
+    Warp,
+    Axum,
+    ActixWeb,
+}
+
+impl Default for Framework {
+    fn default() -> Self {
+        Framework::Warp
+    }
+}
+
+/// The server of the generated API, from the `[server]` section.
+///
+/// ```toml
+/// [server]
+/// framework = "axum"
+/// bind = "127.0.0.1:8080"
+/// path = "/api/graphql"
+/// cors_origins = ["https://app.example.com"]
+/// timeout_ms = 30000
+/// body_limit = 1048576
+/// ```
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Server {
+    pub framework: Framework,
+    /// Address the server listens on, the port is overridden by the `PORT` environment variable.
+    pub bind: String,
+    /// Path of the GraphQL endpoint, subscriptions are served over websocket on the same path.
+    pub path: String,
+    /// Origins allowed by CORS, any origin is allowed when empty.
+    pub cors_origins: Vec<String>,
+    /// Timeout of a request.
+    pub timeout_ms: u64,
+    /// Maximum size of a request body, in bytes.
+    pub body_limit: Option<u64>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server {
+            framework: Framework::default(),
+            bind: "0.0.0.0:8000".to_string(),
+            path: "/graphql".to_string(),
+            cors_origins: Vec::new(),
+            timeout_ms: 10_000,
+            body_limit: None,
+        }
+    }
+}
+
+impl Server {
+    /// Segments of the GraphQL endpoint path.
+    pub fn path_segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|x| !x.is_empty()).collect()
+    }
+
+    /// Path of the GraphQL endpoint, starting with a `/`.
+    pub fn route(&self) -> String {
+        format!("/{}", self.path_segments().join("/"))
+    }
+}
@@ -17,6 +17,10 @@ impl AutoImport for TypeDefinition {
             TypeKind::Interface(_) => {
                 Some((format!("crate::domain::{}", name.to_lowercase()), name))
             }
+            TypeKind::InputObject(_) => {
+                Some((format!("crate::domain::{}", name.to_lowercase()), name))
+            }
+            TypeKind::Scalar => Some(("crate::domain::scalars".to_string(), name)),
             _ => None,
         };
 
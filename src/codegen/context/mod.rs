@@ -2,30 +2,51 @@ use convert_case::{Case, Casing};
 use std::borrow::BorrowMut;
 use std::cell::RefCell;
 use std::cell::RefMut;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::io::{Read, Write};
 use std::rc::Rc;
-use std::{fs::File, path::Path};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
 
 use crate::codegen::generate::GenericErrors;
-use crate::codegen::{config::Config, config::Service, render::graphql::object::ObjectWrapper};
+use crate::codegen::{
+    config::generate_errors_module, config::generate_headers_module,
+    config::generate_resilience_module, config::ArgumentSource, config::BatchArgument,
+    config::Config, config::Method, config::ScalarDependency, config::Service, config::Transport,
+    config::PAGINATION_ARGUMENTS, render::graphql::object::ObjectWrapper,
+};
 use async_graphql_parser::types::{
-    DirectiveDefinition, InterfaceType, SchemaDefinition, ServiceDocument, TypeDefinition,
-    TypeKind, TypeSystemDefinition,
+    BaseType, DirectiveDefinition, FieldDefinition, InputObjectType, InterfaceType,
+    SchemaDefinition, ServiceDocument, Type, TypeDefinition, TypeKind, TypeSystemDefinition,
 };
 use codegen::Scope;
 
 use self::auto_import::AutoImport;
 
-use super::render::cargo::MainFile;
+use super::render::cargo::{generate_build_rs, generate_cargo_toml, MainFile, ProjectFeatures};
+use super::render::graphql::directive::{
+    ServiceBackedConnectionDirective, ServiceBackedNodeDirective,
+};
+use super::render::graphql::field::FieldDefinitionExt;
 use super::render::graphql::input::InputWrapper;
 use super::render::graphql::interfaces::InterfaceWrapper;
+use super::render::graphql::obj::asbru_type::{AsbruObjectExt, AsbruObjectExtErrors};
 use super::render::graphql::r#enum::EnumWrapper;
+use super::render::graphql::scal::asbru_type::AsbruTypeErrors;
+use super::render::graphql::scalars::ToRustType;
 use super::render::graphql::union::UnionWrapper;
 
 pub mod auto_import;
+pub mod recursion;
+pub mod regions;
+
+/// List of the files generated by the last run, relative to the project root, with the
+/// fingerprint of their user regions when they still have their default code.
+const MANIFEST: &str = ".asbru-manifest";
 
 /// The context is like the Scope for the whole codegen, it's where we'll put every options for the
 /// Codegen and every derived settings too.
@@ -39,6 +60,13 @@ pub struct Context<'a> {
     schema: &'a ServiceDocument,
     main_file: RefCell<MainFile>,
     hashpath: RefCell<HashMap<String, String>>,
+    /// Files generated by this run, relative to the project root, with the fingerprint of their
+    /// user regions when they still have their default code.
+    generated: RefCell<BTreeMap<PathBuf, Option<u64>>>,
+    /// Modules declared by each generated `mod.rs`, relative to the project root.
+    modules: RefCell<BTreeMap<PathBuf, BTreeSet<String>>>,
+    /// Fields closing a cycle between types, as `(type, field)`, boxed inside the domain.
+    recursive_fields: HashSet<(String, String)>,
 }
 
 impl<'a> Context<'a> {
@@ -48,16 +76,24 @@ impl<'a> Context<'a> {
         config: &'a Config,
     ) -> Self {
         let output = directory.as_ref();
-        let main_path = output.join(Path::new("src/main.rs"));
+        let lib = config.package().workspace;
+        let main_path = if lib {
+            output.join(Path::new("src/lib.rs"))
+        } else {
+            output.join(Path::new("src/main.rs"))
+        };
 
         let hashpath = RefCell::new(HashMap::new());
 
-        let temp = Self {
+        let mut temp = Self {
             config,
             directory: output,
             schema,
-            main_file: RefCell::new(MainFile::new(&main_path)),
+            main_file: RefCell::new(MainFile::new(&main_path, lib)),
             hashpath,
+            generated: RefCell::new(BTreeMap::new()),
+            modules: RefCell::new(BTreeMap::new()),
+            recursive_fields: HashSet::new(),
         };
 
         let hashpath_2 = &temp.hashpath;
@@ -67,6 +103,12 @@ impl<'a> Context<'a> {
                 hashpath_2.borrow_mut().insert(name, path);
             }
         });
+
+        let recursive_fields = recursion::recursive_fields(&temp.type_definition())
+            .into_iter()
+            .map(|(type_name, field)| (type_name.to_string(), field.to_string()))
+            .collect();
+        temp.recursive_fields = recursive_fields;
         temp
     }
 
@@ -116,16 +158,137 @@ impl<'a> Context<'a> {
             .collect()
     }
 
-    pub fn scalar_types(&self) {
+    /// Custom scalars declared inside the Schema
+    pub fn scalar_names(&self) -> Vec<&str> {
         self.type_definition()
-            .iter()
-            .map(|type_def| match type_def.kind {
-                TypeKind::Scalar => {
-                    println!("{:?}", type_def);
-                }
-                _ => {}
-            })
-            .collect::<Vec<_>>();
+            .into_iter()
+            .filter(|type_def| matches!(type_def.kind, TypeKind::Scalar))
+            .map(|type_def| type_def.name.node.as_str())
+            .collect::<Vec<_>>()
+    }
+
+    /// Check if the given type name is a custom Scalar from the Schema
+    pub fn is_scalar(&self, type_name: &str) -> bool {
+        self.scalar_names().contains(&type_name)
+    }
+
+    /// Cargo dependencies needed by the Rust types of the custom scalars.
+    pub fn scalar_dependencies(&self) -> Vec<ScalarDependency> {
+        self.scalar_names()
+            .into_iter()
+            .filter_map(|name| self.config.scalar_dependency(name))
+            .collect::<Vec<_>>()
+    }
+
+    /// What the generated code uses, to derive the dependencies of the project.
+    pub fn project_features(&self) -> ProjectFeatures {
+        let http = self.config.http_transports();
+
+        ProjectFeatures {
+            http: !http.is_empty(),
+            grpc: !self.config.grpc_transports().is_empty(),
+            sse: http
+                .iter()
+                .any(|x| x.methods().values().any(|method| method.is_sse())),
+            loaders: self
+                .object_types()
+                .iter()
+                .any(|x| matches!(x.doc.service_backed_node(), Ok(Some(_)))),
+            auth: self.config.auth().is_some(),
+            lib: self.config.package().workspace,
+            framework: self.config.server().framework,
+            scalars: self.scalar_dependencies(),
+        }
+    }
+
+    /// Generate the Cargo.toml of the project, named after the output directory unless the
+    /// package config names it.
+    pub fn generate_cargo_toml(&self) -> Result<(), GenericErrors> {
+        let package = self.config.package();
+        let name = package.name.clone().unwrap_or_else(|| {
+            self.directory()
+                .canonicalize()
+                .ok()
+                .as_deref()
+                .and_then(Path::file_name)
+                .map(|x| x.to_string_lossy().to_string().to_case(Case::Kebab))
+                .unwrap_or_else(|| "asbru-generated".to_string())
+        });
+
+        let content = generate_cargo_toml(package, &name, &self.project_features());
+        self.create_a_project_file("Cargo.toml", content.as_bytes())?;
+        Ok(())
+    }
+
+    /// Generate the custom scalars inside `domain/scalars.rs`, each one is a newtype wrapping its
+    /// Rust type and implementing `ScalarType` with its GraphQL name. A scalar without Rust type
+    /// wraps a String.
+    pub fn generate_scalars(&self) -> Result<(), GenericErrors> {
+        let names = self.scalar_names();
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        let mut scope = Scope::new();
+        scope.import("async_graphql", "*");
+        scope.import("serde", "Serialize");
+        scope.import("serde", "Deserialize");
+
+        // Each scalar is a newtype, so the schema exposes it with its own name instead of the one
+        // of its Rust type.
+        for name in names {
+            let (doc, rust_type, derive) = match self.config.scalar_type(name) {
+                Some(rust_type) => (format!("/// GraphQL scalar `{}`.", name), rust_type, ""),
+                None => (
+                    format!(
+                        "/// GraphQL scalar `{}`, it has no Rust type inside the config.",
+                        name
+                    ),
+                    "String".to_string(),
+                    ", Default",
+                ),
+            };
+            scope.raw(&format!(
+                r#"{doc}
+#[derive(Debug, Clone, Serialize, Deserialize{derive})]
+#[serde(transparent)]
+pub struct {name}(pub {rust_type});
+
+#[Scalar(name = "{name}")]
+impl ScalarType for {name} {{
+    fn parse(value: Value) -> InputValueResult<Self> {{
+        <{rust_type} as InputType>::parse(Some(value))
+            .map({name})
+            .map_err(InputValueError::propagate)
+    }}
+
+    fn to_value(&self) -> Value {{
+        InputType::to_value(&self.0)
+    }}
+}}
+
+impl std::ops::Deref for {name} {{
+    type Target = {rust_type};
+
+    fn deref(&self) -> &Self::Target {{
+        &self.0
+    }}
+}}
+
+impl From<{rust_type}> for {name} {{
+    fn from(value: {rust_type}) -> Self {{
+        {name}(value)
+    }}
+}}"#,
+                doc = doc,
+                derive = derive,
+                name = name,
+                rust_type = rust_type,
+            ));
+        }
+
+        self.create_a_new_file("domain/scalars.rs", scope.to_string().as_bytes())?;
+        Ok(())
     }
 
     /// Schema interfaces
@@ -204,6 +367,25 @@ impl<'a> Context<'a> {
             .collect::<Vec<_>>()
     }
 
+    /// Check if the given field closes a cycle between types, it's boxed inside the domain
+    pub fn is_recursive_field(&self, type_name: &str, field_name: &str) -> bool {
+        self.recursive_fields
+            .contains(&(type_name.to_string(), field_name.to_string()))
+    }
+
+    /// Get a type from the Schema by its name
+    pub fn type_definition_by_name(&self, type_name: &str) -> Option<&'a TypeDefinition> {
+        self.schema
+            .definitions
+            .iter()
+            .find_map(|type_def| match type_def {
+                TypeSystemDefinition::Type(n) if n.node.name.node.as_str() == type_name => {
+                    Some(&n.node)
+                }
+                _ => None,
+            })
+    }
+
     /// Check if the given type name is an Enum from the Schema
     pub fn is_enum(&self, type_name: &str) -> bool {
         self.type_definition()
@@ -216,6 +398,265 @@ impl<'a> Context<'a> {
             .is_some()
     }
 
+    /// Get an Input type from the Schema by its name
+    pub fn input_object(&self, type_name: &str) -> Option<&InputObjectType> {
+        self.type_definition()
+            .into_iter()
+            .find(|x| x.name.node.as_str() == type_name)
+            .and_then(|x| match &x.kind {
+                TypeKind::InputObject(input) => Some(input),
+                _ => None,
+            })
+    }
+
+    /// Check if the Schema has a Mutation type with fields to generate
+    pub fn has_mutation(&self) -> bool {
+        self.has_object_fields("Mutation")
+    }
+
+    /// Check if the Schema has a Subscription type with fields to generate
+    pub fn has_subscription(&self) -> bool {
+        self.has_object_fields("Subscription")
+    }
+
+    /// Check if the given Object type exists in the Schema and has fields
+    fn has_object_fields(&self, type_name: &str) -> bool {
+        self.object_definition(type_name)
+            .map(|x| match &x.kind {
+                TypeKind::Object(object) => !object.fields.is_empty(),
+                _ => false,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Rust types of the arguments of a service method, declared inside the config or inferred
+    /// from the GraphQL fields backed by this method: the sources of the mapped arguments, their
+    /// arguments, and the fields of their input object arguments.
+    /// Keys are the snake case names of the arguments, types are imported inside the scope.
+    pub fn method_argument_types(
+        &self,
+        service: &str,
+        method_name: &str,
+        scope: &mut Scope,
+    ) -> Result<HashMap<String, String>, GenericErrors> {
+        let mut types = HashMap::new();
+
+        let method = self
+            .get_service_by_name(service)?
+            .get_a_method(service, method_name)?;
+
+        // Fields backed by this method, with their parent and their arguments mapping.
+        let fields = self
+            .type_definition()
+            .into_iter()
+            .filter_map(|x| match &x.kind {
+                TypeKind::Object(object) => Some((x, &object.fields)),
+                _ => None,
+            })
+            .flat_map(|(parent, fields)| fields.iter().map(move |x| (parent, &x.node)))
+            .filter_map(|(parent, field)| match field.service_backed_method() {
+                Ok(Some((s, m, args))) if s == service && m == method_name => {
+                    Some(Ok((parent, field, method.arguments_mapping().merge(&args))))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(AsbruTypeErrors::from(e))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Pagination arguments of a HTTP method backing a Connection are optional: only those
+        // given to the field are sent. A gRPC message has no presence, they are defaults there.
+        let backs_connection = self.type_definition().into_iter().any(|x| {
+            matches!(
+                x.service_backed_connection(),
+                Ok(Some(connection)) if connection.service == service && connection.method_name == method_name
+            )
+        });
+        if backs_connection && matches!(method, Method::HTTP(_)) {
+            for argument in method.argument_names() {
+                if PAGINATION_ARGUMENTS.contains(&argument.as_str())
+                    && !method.is_route_argument(&argument)
+                {
+                    types.insert(argument.to_case(Case::Snake), "Option<String>".to_string());
+                }
+            }
+        }
+
+        // A method backing a node receives the ids of a whole batch. Inside the query string they
+        // are joined with commas and keep the default type.
+        let backs_node = self.type_definition().into_iter().any(|x| {
+            matches!(
+                x.service_backed_node(),
+                Ok(Some(node)) if node.service == service && node.method_name == method_name
+            )
+        });
+        if backs_node {
+            if let Ok(BatchArgument::List(argument)) = method.batch_argument() {
+                types.insert(argument.to_case(Case::Snake), "Vec<String>".to_string());
+            }
+        }
+
+        // Types declared inside the config first, then mapped arguments and field arguments,
+        // they take precedence over the input object fields.
+        for (name, declared) in method.declared_types() {
+            let ty = Type::new(declared).ok_or_else(|| {
+                GenericErrors::InvalidArgumentTypeError(name.to_owned(), declared.to_owned())
+            })?;
+            self.insert_argument_type(&mut types, name, &ty, scope)?;
+        }
+
+        for (parent, field, mapping) in fields.iter() {
+            for root in mapping.roots() {
+                let source_type = match mapping.get(root) {
+                    Some(ArgumentSource::Argument(name)) => self.argument_type(field, name),
+                    Some(ArgumentSource::Parent(name)) => match &parent.kind {
+                        TypeKind::Object(object) => object
+                            .fields
+                            .iter()
+                            .find(|x| x.node.name.node.as_str() == name)
+                            .map(|x| &x.node.ty.node),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                match (source_type, mapping.get(root)) {
+                    (Some(ty), _) => self.insert_argument_type(&mut types, root, &ty, scope)?,
+                    (None, Some(ArgumentSource::Constant(value))) => {
+                        let rust_type = match value {
+                            serde_json::Value::String(_) => "String",
+                            serde_json::Value::Bool(_) => "bool",
+                            serde_json::Value::Number(x) if x.is_f64() => "f64",
+                            serde_json::Value::Number(_) => "i64",
+                            _ => "serde_json::Value",
+                        };
+                        types
+                            .entry(root.to_case(Case::Snake))
+                            .or_insert_with(|| rust_type.to_string());
+                    }
+                    // Nested paths are sent as a JSON object.
+                    (None, None) => {
+                        types
+                            .entry(root.to_case(Case::Snake))
+                            .or_insert_with(|| "serde_json::Value".to_string());
+                    }
+                    _ => {}
+                };
+            }
+        }
+
+        for (_, field, _) in fields.iter() {
+            for argument in field.arguments.iter() {
+                self.insert_argument_type(
+                    &mut types,
+                    argument.node.name.node.as_str(),
+                    &argument.node.ty.node,
+                    scope,
+                )?;
+            }
+        }
+
+        for (_, field, _) in fields.iter() {
+            for argument in field.arguments.iter() {
+                let ty = &argument.node.ty.node;
+                let input = match &ty.base {
+                    BaseType::Named(name) if !ty.nullable => self.input_object(name.as_str()),
+                    _ => None,
+                };
+
+                for input_field in input.iter().flat_map(|x| x.fields.iter()) {
+                    self.insert_argument_type(
+                        &mut types,
+                        input_field.node.name.node.as_str(),
+                        &input_field.node.ty.node,
+                        scope,
+                    )?;
+                }
+            }
+        }
+
+        Ok(types)
+    }
+
+    /// Insert the Rust type of an argument if it's not already known, and import it inside the
+    /// scope.
+    fn insert_argument_type(
+        &self,
+        types: &mut HashMap<String, String>,
+        name: &str,
+        ty: &Type,
+        scope: &mut Scope,
+    ) -> Result<(), GenericErrors> {
+        let name = name.to_case(Case::Snake);
+        if !types.contains_key(&name) {
+            if ty.entity_type() == "ID" {
+                scope.import("async_graphql", "ID");
+            } else {
+                self.import_path(ty.entity_type(), scope);
+            }
+            types.insert(name, ty.to_rust_type(None)?);
+        }
+        Ok(())
+    }
+
+    /// GraphQL type of an argument of a field, or of a field of its input object arguments.
+    fn argument_type<'b>(&'b self, field: &'b FieldDefinition, name: &str) -> Option<&'b Type> {
+        let argument = field
+            .arguments
+            .iter()
+            .find(|x| x.node.name.node.as_str() == name)
+            .map(|x| &x.node.ty.node);
+
+        argument.or_else(|| {
+            field
+                .arguments
+                .iter()
+                .filter(|x| !x.node.ty.node.nullable)
+                .filter_map(|x| match &x.node.ty.node.base {
+                    BaseType::Named(input) => self.input_object(input.as_str()),
+                    _ => None,
+                })
+                .flat_map(|input| input.fields.iter())
+                .find(|x| x.node.name.node.as_str() == name)
+                .map(|x| &x.node.ty.node)
+        })
+    }
+
+    /// Get an Object type from the Schema by its name
+    fn object_definition(&self, type_name: &str) -> Option<&TypeDefinition> {
+        self.type_definition()
+            .into_iter()
+            .find(|x| x.name.node.as_str() == type_name)
+            .filter(|x| matches!(x.kind, TypeKind::Object(_)))
+    }
+
+    /// Get the `@serviceBackedNode` directive of the given Object type from the Schema
+    pub fn service_backed_node(
+        &self,
+        type_name: &str,
+    ) -> Result<Option<ServiceBackedNodeDirective>, AsbruObjectExtErrors> {
+        match self.object_definition(type_name) {
+            Some(type_def) => type_def.service_backed_node(),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the `@serviceBackedConnection` directive of the given Connection type from the Schema
+    pub fn service_backed_connection(
+        &self,
+        type_name: &str,
+    ) -> Result<Option<ServiceBackedConnectionDirective>, AsbruObjectExtErrors> {
+        match self.object_definition(type_name) {
+            Some(type_def) => type_def.service_backed_connection(),
+            None => Ok(None),
+        }
+    }
+
+    /// Check if the given type name is an Object from the Schema backed by a
+    /// `@serviceBackedNode` directive
+    pub fn is_service_backed_node(&self, type_name: &str) -> bool {
+        matches!(self.service_backed_node(type_name), Ok(Some(_)))
+    }
+
     pub fn directory(&self) -> &Path {
         self.directory
     }
@@ -224,7 +665,7 @@ impl<'a> Context<'a> {
     /// The path must be relative to src/
     /// If u want to create a file into src/domain/test.rs path must be "domain/test.rs".
     ///
-    /// Create folders if they do not exists.
+    /// Create folders if they do not exists. The `mod.rs` files are written by `finalize`.
     pub fn create_a_new_file<P: AsRef<Path>>(
         &self,
         path: P,
@@ -239,15 +680,14 @@ impl<'a> Context<'a> {
             fs::create_dir_all(&src.parent().unwrap())?;
         }
 
-        let mut f = fs::File::create(&src)?;
-        f.write_all(&content)?;
+        let f = self.write_generated_file(&src, content)?;
 
         let relative_to_directory = src
             .strip_prefix(self.directory())
             .unwrap_or(self.directory());
         let paths: Vec<&str> = relative_to_directory.to_str().unwrap().split('/').collect();
 
-        let mut src = output.to_path_buf();
+        let mut src = PathBuf::new();
 
         let path_len = paths.len();
 
@@ -260,60 +700,182 @@ impl<'a> Context<'a> {
             let mod_name = paths[i + 1].trim_end_matches(".rs");
 
             if i == 0 {
-                let already = self.main_file().main_scope().to_string();
-                let pattern_to_write = format!("mod {};", &mod_name);
-
-                if already.find(&pattern_to_write).is_none() {
-                    self.main_file()
-                        .main_scope()
-                        .raw(&format!("mod {};", mod_name));
-                }
+                self.main_file().module(mod_name);
                 continue;
             }
 
-            let file_path = src.clone().join("mod.rs");
-            let should_write = match fs::read_to_string(&file_path) {
-                Ok(content) => content.find(&format!("pub mod {};\n", mod_name)).is_none(),
-                _ => true,
-            };
+            self.modules
+                .borrow_mut()
+                .entry(src.join("mod.rs"))
+                .or_default()
+                .insert(mod_name.to_string());
+        }
+
+        Ok(f)
+    }
+
+    /// Create a new file inside the generated project, outside of the crate modules.
+    /// The path must be relative to the project root.
+    ///
+    /// Create folders if they do not exists.
+    pub fn create_a_project_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        content: &[u8],
+    ) -> Result<File, io::Error> {
+        let file_path = self.directory().join(path);
+        fs::create_dir_all(&file_path.parent().unwrap())?;
+
+        self.write_generated_file(&file_path, content)
+    }
+
+    /// Write a generated file and add it to the manifest, the user regions of the previous
+    /// version of the file are carried over.
+    fn write_generated_file(&self, path: &Path, content: &[u8]) -> Result<File, io::Error> {
+        let generated = String::from_utf8_lossy(content);
+        let content = match fs::read_to_string(path) {
+            Ok(previous) => regions::carry_over(&previous, &generated),
+            Err(_) => generated.to_string(),
+        };
 
-            if should_write {
-                let mut path_file = fs::OpenOptions::new()
-                    .append(true)
-                    .create(true)
-                    .open(&file_path)?;
+        let mut f = fs::File::create(path)?;
+        f.write_all(content.as_bytes())?;
+        println!("Processing {:?}", path);
 
-                path_file.write_all(format!("pub mod {};\n", mod_name).as_bytes())?;
-                println!("Processing {:?}", &file_path);
+        self.generated.borrow_mut().insert(
+            path.strip_prefix(self.directory())
+                .unwrap_or(path)
+                .to_path_buf(),
+            regions::default_fingerprint(&content, &generated),
+        );
+
+        Ok(f)
+    }
+
+    /// Finish the generation once every file is generated:
+    /// - Write the `mod.rs` files, hand-written modules are kept inside their user region.
+    /// - Remove the files generated by the previous run which are not generated anymore, unless
+    ///   their user regions changed since they had their default code, then they are only
+    ///   reported.
+    /// - Write the manifest of the generated files.
+    pub fn finalize(&self) -> Result<(), GenericErrors> {
+        for (path, modules) in self.modules.borrow().iter() {
+            let content = format!(
+                "{}{}\n",
+                modules
+                    .iter()
+                    .map(|x| format!("pub mod {};\n", x))
+                    .collect::<String>(),
+                regions::user_region("modules", "")
+            );
+            self.write_generated_file(&self.directory().join(path), content.as_bytes())?;
+        }
+
+        let manifest = self.directory().join(MANIFEST);
+        let previous = fs::read_to_string(&manifest).unwrap_or_default();
+        let mut generated = self.generated.borrow_mut();
+        let mut kept = Vec::new();
+
+        for (path, defaults) in previous
+            .lines()
+            .map(|x| match x.split_once('\t') {
+                Some((path, defaults)) => {
+                    (PathBuf::from(path), u64::from_str_radix(defaults, 16).ok())
+                }
+                None => (PathBuf::from(x), None),
+            })
+            .filter(|(x, _)| !generated.contains_key(x))
+        {
+            let file_path = self.directory().join(&path);
+            match fs::read_to_string(&file_path) {
+                Ok(content) if regions::has_user_code(&content, defaults) => {
+                    println!(
+                        "Warning: {:?} is not generated anymore but has hand-written code, remove it once migrated",
+                        &file_path
+                    );
+                    kept.push((path, defaults));
+                }
+                Ok(_) => {
+                    fs::remove_file(&file_path)?;
+                    println!("Removing stale {:?}", &file_path);
+                }
+                Err(_) => {}
             }
         }
 
-        Ok(f)
+        // Kept files stay inside the manifest so they are reported until removed.
+        generated.extend(kept);
+        let content = generated
+            .iter()
+            .map(|(path, defaults)| match defaults {
+                Some(defaults) => format!("{}\t{:x}\n", path.display(), defaults),
+                None => format!("{}\n", path.display()),
+            })
+            .collect::<String>();
+        fs::write(&manifest, content)?;
+
+        Ok(())
     }
 
     /// Generate Service file
-    /// TODO Generate the service based on the transport definition
+    /// The service is generated based on the transport definition, a gRPC service also copies its
+    /// `.proto` inside the project so it's compiled by the `build.rs`.
     fn generate_service_file(
         &self,
         service_name: &str,
         service: &Service,
     ) -> Result<(), GenericErrors> {
         let mut scope = Scope::new();
-        scope.import("reqwest", "Client");
-        scope.import("serde::de", "DeserializeOwned");
-
-        for (method_name, method) in service.methods().iter() {
-            method.generate_method(
-                &mut scope,
-                service.endpoint(),
-                &format!("{}_{}_method", service_name, method_name),
-            )
-        }
+
+        match service.transport() {
+            Transport::HTTP(http) => {
+                let argument_types = http
+                    .methods()
+                    .keys()
+                    .map(|method_name| {
+                        self.method_argument_types(service_name, method_name, &mut scope)
+                            .map(|types| (method_name.to_owned(), types))
+                    })
+                    .collect::<Result<HashMap<_, _>, _>>()?;
+
+                http.generate_service(
+                    service_name,
+                    service.client(),
+                    service.resilience(),
+                    &argument_types,
+                    &mut scope,
+                );
+            }
+            Transport::GRPC(grpc) => {
+                let argument_types = grpc
+                    .methods()
+                    .keys()
+                    .map(|method_name| {
+                        self.method_argument_types(service_name, method_name, &mut scope)
+                            .map(|types| (method_name.to_owned(), types))
+                    })
+                    .collect::<Result<HashMap<_, _>, _>>()?;
+
+                let proto = fs::read(grpc.proto()).map_err(GenericErrors::NotFoundError)?;
+                self.create_a_project_file(
+                    Path::new("proto").join(grpc.proto_file_name()),
+                    &proto,
+                )?;
+                grpc.generate_service(
+                    service_name,
+                    service.client(),
+                    service.resilience(),
+                    &argument_types,
+                    &mut scope,
+                );
+            }
+        };
 
         self.create_a_new_file(
             format!("infrastructure/{}.rs", service_name),
             scope.to_string().as_bytes(),
         )?;
+        self.main_file().service_client(service_name);
 
         Ok(())
     }
@@ -334,8 +896,65 @@ impl<'a> Context<'a> {
             .services()
             .iter()
             .map(|(service_name, service)| self.generate_service_file(&service_name, service))
-            .collect::<Result<Vec<_>, _>>()
-            .map(|_| ())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Incoming headers are stored inside the request data by the server, whatever the services.
+        self.create_a_new_file(
+            "infrastructure/headers.rs",
+            generate_headers_module().as_bytes(),
+        )?;
+        self.create_a_new_file(
+            "infrastructure/errors.rs",
+            generate_errors_module().as_bytes(),
+        )?;
+        self.create_a_new_file(
+            "infrastructure/resilience.rs",
+            generate_resilience_module().as_bytes(),
+        )?;
+
+        if let Some(auth) = self.config.auth() {
+            self.create_a_new_file(
+                "infrastructure/auth.rs",
+                auth.generate_auth_module().as_bytes(),
+            )?;
+            let main_file = self.main_file();
+            main_file
+                .main_scope()
+                .import("infrastructure::auth", "authenticator");
+            main_file.schema_data("authenticator()?");
+        }
+
+        let protos = self
+            .config
+            .grpc_transports()
+            .iter()
+            .map(|grpc| format!("proto/{}", grpc.proto_file_name()))
+            .collect::<Vec<String>>();
+        let mut packages = self
+            .config
+            .grpc_transports()
+            .iter()
+            .map(|grpc| grpc.package().to_string())
+            .collect::<Vec<String>>();
+        packages.sort();
+        packages.dedup();
+
+        if !protos.is_empty() {
+            self.create_a_project_file(
+                "build.rs",
+                generate_build_rs(&protos, &packages).as_bytes(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the generated main.rs, or the lib.rs of a library crate, and add it to the manifest:
+    /// the other one is reported or removed as stale when switching between both.
+    pub fn write_main_file(&self, content: &str) -> Result<(), GenericErrors> {
+        let path = self.main_file().path().to_path_buf();
+        self.write_generated_file(&path, content.as_bytes())?;
+        Ok(())
     }
 
     /// Write to the main_file
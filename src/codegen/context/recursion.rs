@@ -0,0 +1,184 @@
+//! Recursion
+//! A domain struct embeds the domain representation of its fields, a cycle between types would
+//! make them infinitely sized. The fields closing a cycle are boxed, lists are already stored on
+//! the heap and nodes are stored as ids resolved by their DataLoader.
+use std::collections::HashSet;
+
+use async_graphql_parser::types::{BaseType, TypeDefinition, TypeKind};
+
+use crate::codegen::render::graphql::interfaces::interface_ancestors;
+
+/// Field directives replacing the domain representation of a field.
+const NOT_EMBEDDED_DIRECTIVES: [&str; 2] = ["derivedField", "fromNumber"];
+
+fn definition<'a>(definitions: &[&'a TypeDefinition], name: &str) -> Option<&'a TypeDefinition> {
+    definitions
+        .iter()
+        .find(|x| x.name.node.as_str() == name)
+        .copied()
+}
+
+/// Types embedded by value inside the domain representation of a type, with the field embedding
+/// them. Variants of interfaces and unions have no field.
+fn embedded_types<'a>(
+    type_def: &'a TypeDefinition,
+    definitions: &[&'a TypeDefinition],
+) -> Vec<(Option<&'a str>, &'a str)> {
+    match &type_def.kind {
+        TypeKind::Object(object) => object
+            .fields
+            .iter()
+            .map(|x| &x.node)
+            .filter(|x| x.arguments.is_empty())
+            .filter(|x| {
+                !x.directives
+                    .iter()
+                    .any(|x| NOT_EMBEDDED_DIRECTIVES.contains(&x.node.name.node.as_str()))
+            })
+            .filter_map(|x| match &x.ty.node.base {
+                BaseType::Named(name) => Some((Some(x.name.node.as_str()), name.as_str())),
+                BaseType::List(_) => None,
+            })
+            .filter(|(_, name)| match definition(definitions, name) {
+                Some(target) => !target
+                    .directives
+                    .iter()
+                    .any(|x| x.node.name.node.as_str() == "serviceBackedNode"),
+                None => false,
+            })
+            .collect(),
+        TypeKind::Interface(_) => {
+            let name = type_def.name.node.as_str();
+            let lookup = |name: &str| definition(definitions, name);
+            definitions
+                .iter()
+                .copied()
+                .filter(|x| matches!(x.kind, TypeKind::Object(_)))
+                .filter(|x| interface_ancestors(*x, lookup).contains(&name))
+                .map(|x| (None, x.name.node.as_str()))
+                .collect()
+        }
+        TypeKind::Union(union) => union
+            .members
+            .iter()
+            .map(|x| (None, x.node.as_str()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Tell if `to` is embedded by `from`, directly or through other types.
+fn embeds(from: &str, to: &str, definitions: &[&TypeDefinition]) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![from];
+
+    while let Some(name) = stack.pop() {
+        if name == to {
+            return true;
+        }
+        if !visited.insert(name) {
+            continue;
+        }
+        if let Some(type_def) = definition(definitions, name) {
+            stack.extend(
+                embedded_types(type_def, definitions)
+                    .into_iter()
+                    .map(|(_, x)| x),
+            );
+        }
+    }
+
+    false
+}
+
+/// Fields closing a cycle between types, as `(type, field)`: they are boxed inside the domain.
+/// Every field of a cycle embedding a type of the same cycle is boxed so every cycle is broken
+/// whatever the type we start from.
+pub fn recursive_fields<'a>(definitions: &[&'a TypeDefinition]) -> Vec<(&'a str, &'a str)> {
+    definitions
+        .iter()
+        .copied()
+        .flat_map(|type_def| {
+            let type_name = type_def.name.node.as_str();
+            embedded_types(type_def, definitions)
+                .into_iter()
+                .filter_map(move |(field, target)| field.map(|field| (type_name, field, target)))
+        })
+        .filter(|(type_name, _, target)| embeds(target, type_name, definitions))
+        .map(|(type_name, field, _)| (type_name, field))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_graphql_parser::{parse_schema, types::TypeSystemDefinition};
+
+    fn recursive(schema: &str) -> Vec<(String, String)> {
+        let schema = parse_schema(schema).unwrap();
+        let definitions = schema
+            .definitions
+            .iter()
+            .filter_map(|x| match x {
+                TypeSystemDefinition::Type(x) => Some(&x.node),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut fields = recursive_fields(&definitions)
+            .into_iter()
+            .map(|(type_name, field)| (type_name.to_string(), field.to_string()))
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_recursive_fields() {
+        let fields = recursive(
+            r#"
+            type User {
+              id: ID!
+              bestFriend: User
+              team: Team
+            }
+
+            type Team {
+              id: ID!
+              lead: User!
+            }
+            "#,
+        );
+
+        assert_eq!(
+            fields,
+            vec![
+                ("Team".to_string(), "lead".to_string()),
+                ("User".to_string(), "bestFriend".to_string()),
+                ("User".to_string(), "team".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_embedded_fields() {
+        // Lists are on the heap, nodes are ids and derived fields are not stored.
+        let fields = recursive(
+            r#"
+            type User {
+              id: ID!
+              friends: [User!]!
+              manager: Manager
+              mentor: User @derivedField(provider: "crate::derived::mentor")
+            }
+
+            type Manager @serviceBackedNode(service: "users", methodName: "managerById") {
+              id: ID!
+              report: User
+            }
+            "#,
+        );
+
+        assert!(fields.is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+//! User regions
+//! Generated files are rewritten on every run, the code written by hand inside a user region is
+//! carried over from the previous version of the file.
+//!
+//! ```
+//! // asbru:begin(pet)
+//! todo!("WIP")
+//! // asbru:end(pet)
+//! ```
+use std::collections::HashMap;
+
+const BEGIN: &str = "// asbru:begin(";
+const END: &str = "// asbru:end(";
+
+/// Wrap the default code of a user region, `id` must be unique inside the file.
+pub fn user_region(id: &str, default: &str) -> String {
+    let default = default.trim();
+    if default.is_empty() {
+        return format!(
+            "{begin}{id})\n{end}{id})",
+            begin = BEGIN,
+            end = END,
+            id = id
+        );
+    }
+
+    format!(
+        "{begin}{id})\n{default}\n{end}{id})",
+        begin = BEGIN,
+        end = END,
+        id = id,
+        default = default
+    )
+}
+
+/// Id of the region opened or closed by this line, if any.
+fn marker<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.trim()
+        .strip_prefix(prefix)
+        .and_then(|x| x.strip_suffix(')'))
+}
+
+/// Lines of every user region of a file, by id.
+fn user_regions(content: &str) -> HashMap<&str, Vec<&str>> {
+    let mut regions = HashMap::new();
+    let mut current: Option<(&str, Vec<&str>)> = None;
+
+    for line in content.lines() {
+        current = match current {
+            Some((id, lines)) if marker(line, END) == Some(id) => {
+                regions.insert(id, lines);
+                None
+            }
+            Some((id, mut lines)) => {
+                lines.push(line);
+                Some((id, lines))
+            }
+            None => marker(line, BEGIN).map(|id| (id, Vec::new())),
+        };
+    }
+
+    regions
+}
+
+/// Replace the user regions of a generated file by the ones of its previous version.
+/// Regions which are not generated anymore are dropped.
+pub fn carry_over(previous: &str, generated: &str) -> String {
+    let previous = user_regions(previous);
+    let mut content = Vec::new();
+    let mut skipping = None;
+
+    for line in generated.lines() {
+        if let Some(id) = skipping {
+            if marker(line, END) == Some(id) {
+                content.push(line.to_string());
+                skipping = None;
+            }
+            continue;
+        }
+
+        content.push(line.to_string());
+
+        if let Some(id) = marker(line, BEGIN) {
+            if let Some(lines) = previous.get(id) {
+                content.extend(lines.iter().map(|x| x.to_string()));
+                skipping = Some(id);
+            }
+        }
+    }
+
+    let mut content = content.join("\n");
+    if generated.ends_with('\n') {
+        content.push('\n');
+    }
+    content
+}
+
+/// Fingerprint of the user regions of a file, FNV-1a so it's stable between runs.
+fn fingerprint(content: &str) -> u64 {
+    let mut regions = user_regions(content).into_iter().collect::<Vec<_>>();
+    regions.sort();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (id, lines) in regions {
+        for line in std::iter::once(id).chain(lines) {
+            for byte in line.bytes().chain(std::iter::once(b'\n')) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+    }
+    hash
+}
+
+/// Fingerprint of the user regions of a written file, when they all still have the default code
+/// they were `generated` with.
+pub fn default_fingerprint(content: &str, generated: &str) -> Option<u64> {
+    if user_regions(content) == user_regions(generated) {
+        Some(fingerprint(content))
+    } else {
+        None
+    }
+}
+
+/// Tell if a file has hand-written code: its user regions changed since they were written with
+/// their default code, whose fingerprint is `defaults`. Without it, every region is hand-written.
+pub fn has_user_code(content: &str, defaults: Option<u64>) -> bool {
+    defaults != Some(fingerprint(content))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GENERATED: &str = "use crate::domain::pet::Pet;
+// asbru:begin(use)
+// asbru:end(use)
+
+fn pet() -> Pet {
+    // asbru:begin(pet)
+    todo!(\"WIP\")
+    // asbru:end(pet)
+}
+";
+
+    #[test]
+    fn test_user_regions() {
+        let regions = user_regions(GENERATED);
+
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions["use"], Vec::<&str>::new());
+        assert_eq!(regions["pet"], vec!["    todo!(\"WIP\")"]);
+    }
+
+    #[test]
+    fn test_user_regions_unclosed() {
+        let regions = user_regions("// asbru:begin(pet)\nlet pet = Pet::default();\n");
+
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn test_carry_over() {
+        let previous = GENERATED
+            .replace(
+                "// asbru:end(use)",
+                "use crate::pets::find;\n// asbru:end(use)",
+            )
+            .replace("todo!(\"WIP\")", "find()");
+        let generated = format!(
+            "{}\n// asbru:begin(owner)\ntodo!(\"WIP\")\n// asbru:end(owner)\n",
+            GENERATED.trim_end()
+        );
+
+        let content = carry_over(&previous, &generated);
+        let regions = user_regions(&content);
+
+        assert_eq!(regions["use"], vec!["use crate::pets::find;"]);
+        assert_eq!(regions["pet"], vec!["    find()"]);
+        assert_eq!(regions["owner"], vec!["todo!(\"WIP\")"]);
+        assert!(content.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_carry_over_dropped_region() {
+        let previous = GENERATED.replace("todo!(\"WIP\")", "find()");
+        let generated = "// asbru:begin(use)\n// asbru:end(use)\n";
+
+        assert_eq!(carry_over(&previous, generated), generated);
+    }
+
+    #[test]
+    fn test_has_user_code() {
+        let defaults = default_fingerprint(GENERATED, GENERATED);
+
+        assert!(defaults.is_some());
+        assert!(!has_user_code(GENERATED, defaults));
+        assert!(has_user_code(GENERATED, None));
+    }
+
+    #[test]
+    fn test_has_user_code_with_todo() {
+        // Hand-written code still having a `todo!()` is hand-written code.
+        let content =
+            GENERATED.replace("todo!(\"WIP\")", "let owner = find();\n    todo!(\"WIP\")");
+
+        assert!(has_user_code(
+            &content,
+            default_fingerprint(GENERATED, GENERATED)
+        ));
+    }
+
+    #[test]
+    fn test_default_fingerprint_carried_over() {
+        let previous = GENERATED.replace("todo!(\"WIP\")", "find()");
+        let content = carry_over(&previous, GENERATED);
+
+        assert_eq!(default_fingerprint(&content, GENERATED), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Diagnostics
+//! Errors and warnings found inside the schema or the config, located inside their file and
+//! printed compiler-style with the offending line.
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::codegen::generate::GenericErrors;
+
+/// A file read by Asbru, kept to locate diagnostics.
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl SourceFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, GenericErrors> {
+        let content = fs::read_to_string(&path).map_err(GenericErrors::NotFoundError)?;
+        Ok(SourceFile {
+            path: path.as_ref().to_path_buf(),
+            content,
+        })
+    }
+
+    /// Line of the first occurrence of `pattern`, starting at 1.
+    pub fn find_line(&self, pattern: &str) -> Option<usize> {
+        self.content
+            .lines()
+            .position(|x| x.contains(pattern))
+            .map(|x| x + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Something Asbru handled for you, like a recursive field being boxed.
+    Note,
+}
+
+/// Where a diagnostic is, lines and columns start at 1.
+#[derive(Debug, Clone)]
+struct Location {
+    path: PathBuf,
+    line: usize,
+    column: usize,
+    /// The offending line.
+    source: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    message: String,
+    location: Option<Location>,
+}
+
+impl Diagnostic {
+    pub fn error<S: ToString>(message: S) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.to_string(),
+            location: None,
+        }
+    }
+
+    pub fn warning<S: ToString>(message: S) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message: message.to_string(),
+            location: None,
+        }
+    }
+
+    pub fn note<S: ToString>(message: S) -> Self {
+        Diagnostic {
+            severity: Severity::Note,
+            message: message.to_string(),
+            location: None,
+        }
+    }
+
+    /// Locate the diagnostic inside a file, lines and columns start at 1.
+    pub fn at(mut self, file: &SourceFile, line: usize, column: usize) -> Self {
+        self.location = Some(Location {
+            path: file.path.clone(),
+            line,
+            column,
+            source: file
+                .content
+                .lines()
+                .nth(line.saturating_sub(1))
+                .map(|x| x.to_string()),
+        });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        writeln!(f, "{}: {}", severity, self.message)?;
+
+        if let Some(location) = &self.location {
+            let gutter = " ".repeat(location.line.to_string().len());
+            writeln!(
+                f,
+                "{}--> {}:{}:{}",
+                gutter,
+                location.path.display(),
+                location.line,
+                location.column
+            )?;
+
+            if let Some(source) = &location.source {
+                writeln!(f, "{} |", gutter)?;
+                writeln!(f, "{} | {}", location.line, source)?;
+                writeln!(
+                    f,
+                    "{} | {}^",
+                    gutter,
+                    " ".repeat(location.column.saturating_sub(1))
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
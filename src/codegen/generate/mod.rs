@@ -1,9 +1,11 @@
 use crate::codegen::config::Config;
 use crate::codegen::context::Context;
-use crate::codegen::render::cargo::generate_cargo_toml;
+use crate::codegen::diagnostic::{Diagnostic, SourceFile};
+use crate::codegen::render::graphql::obj;
 use crate::codegen::render::graphql::scal;
 use crate::codegen::render::render::Render;
-use async_graphql_parser::{parse_schema, types::ServiceDocument};
+use crate::codegen::validate::check;
+use async_graphql_parser::{parse_schema, types::ServiceDocument, Pos};
 use std::fs;
 use std::io;
 use std::path::Path;
@@ -13,99 +15,790 @@ use thiserror::Error;
 pub enum GenericErrors {
     #[error("IO issue while trying to access schema")]
     NotFoundError(io::Error),
-    #[error("Parser error")]
-    ParserError(#[from] async_graphql_parser::Error),
     #[error("Can't create the output directory")]
     CreateOutputDirectoryError(io::Error),
     #[error("Generator error")]
     GenericGeneratorError,
     #[error("Generic IO issue")]
     GenericIOError(#[from] io::Error),
-    #[error("Config file invalid")]
-    InvalidConfigError,
+    #[error("The schema or the config is invalid")]
+    DiagnosticsError(Vec<Diagnostic>),
     #[error("Service {0} not found")]
     ServiceNotFoundError(String),
-    #[error("Asbru type error")]
+    #[error("Method {1} not found in service {0}")]
+    MethodNotFoundError(String, String),
+    #[error("Method {1} of service {0} has no stream configured to back a subscription")]
+    MethodNotStreamableError(String, String),
+    #[error("Invalid type {1} declared for the argument {0}")]
+    InvalidArgumentTypeError(String, String),
+    #[error("A @serviceBackedNode method can't load a batch of nodes: {0}")]
+    InvalidNodeMethodError(String),
+    #[error(transparent)]
     AsbruTypeError(#[from] scal::asbru_type::AsbruTypeErrors),
+    #[error(transparent)]
+    AsbruObjectError(#[from] obj::asbru_type::AsbruObjectExtErrors),
 }
 
-/// Open a file
-fn open<P: AsRef<Path>>(path: P) -> Result<String, GenericErrors> {
-    fs::read_to_string(path).map_err(GenericErrors::NotFoundError)
+/// Open and parse a schema file, a parser error is located inside the file.
+pub(crate) fn load_schema<P: AsRef<Path>>(
+    path: P,
+) -> Result<(SourceFile, ServiceDocument), GenericErrors> {
+    let file = SourceFile::open(path)?;
+    let schema = parse_schema(&file.content).map_err(|e| {
+        let diagnostic = Diagnostic::error(&e);
+        let diagnostic = match e.positions().next() {
+            Some(pos) => diagnostic.at(&file, pos.line, pos.column),
+            None => diagnostic,
+        };
+        GenericErrors::DiagnosticsError(vec![diagnostic])
+    })?;
+
+    Ok((file, schema))
+}
+
+/// Open and parse a config file, a parser error is located inside the file.
+pub(crate) fn load_config<P: AsRef<Path>>(path: P) -> Result<(SourceFile, Config), GenericErrors> {
+    let file = SourceFile::open(path)?;
+    let config = toml::from_str::<Config>(&file.content).map_err(|e| {
+        let diagnostic = Diagnostic::error(&e);
+        // `toml` lines and columns start at 0.
+        let diagnostic = match e.line_col() {
+            Some((line, column)) => diagnostic.at(&file, line + 1, column + 1),
+            None => diagnostic,
+        };
+        GenericErrors::DiagnosticsError(vec![diagnostic])
+    })?;
+
+    Ok((file, config))
 }
 
-/// Parse a file with a GraphQL Parser
-fn parse<S: AsRef<str>>(schema: S) -> Result<ServiceDocument, GenericErrors> {
-    parse_schema(&schema).map_err(GenericErrors::ParserError)
+/// Locate an error raised while generating a type: at the field or the value which raised it when
+/// known, at the type otherwise.
+fn located_diagnostic(file: &SourceFile, pos: Pos, error: GenericErrors) -> Diagnostic {
+    let pos = match &error {
+        GenericErrors::AsbruTypeError(scal::asbru_type::AsbruTypeErrors::LocatedError(
+            _,
+            pos,
+            _,
+        )) => *pos,
+        _ => pos,
+    };
+    Diagnostic::error(error).at(file, pos.line, pos.column)
 }
 
+/// Generate the project, the schema is checked against the config first: warnings are printed
+/// and errors are returned all together.
 pub fn generate<P: AsRef<Path>>(path: P, output: P, config: P) -> Result<(), GenericErrors> {
-    let schema = open(&path).and_then(parse)?;
-    let config = open(&config).and_then(|config_str| {
-        toml::from_str::<Config>(&config_str).map_err(|_| GenericErrors::InvalidConfigError)
-    })?;
+    let (schema_file, schema) = load_schema(&path)?;
+    let (config_file, config) = load_config(&config)?;
+
+    let (errors, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) =
+        check(&schema_file, &schema, &config_file, &config)
+            .into_iter()
+            .partition(|x| x.is_error());
+    warnings.iter().for_each(|x| println!("{}", x));
+    if !errors.is_empty() {
+        return Err(GenericErrors::DiagnosticsError(errors));
+    }
+
     let context = Context::new(&output, &schema, &config);
 
     // Create a directory with src folder
     let src = output.as_ref().join(Path::new("src/"));
     fs::create_dir_all(src).map_err(GenericErrors::CreateOutputDirectoryError)?;
 
-    // Create a Cargo.toml
-    // Maybe: The Cargo.toml should be generated last, because we'll be able to describe what we are using
-    // in every other files, and generate the dependencies from it.
-    generate_cargo_toml(output.as_ref().join(Path::new("Cargo.toml")));
-
     // For each entity -> Create
     // Object type -> likely to be type in the Schema,
     // so we need to create a domain object type
     // we also need the application type
     // we should also add the directive of how it's called
 
-    let enum_result = context
+    context.generate_scalars()?;
+
+    // Every type is generated, then the errors are reported all together.
+    let errors = context
         .enum_types()
         .iter()
-        .map(|x| x.generate())
+        .map(|x| (x.doc.name.pos, x.generate()))
+        .chain(
+            context
+                .object_types()
+                .iter()
+                .map(|x| (x.doc.name.pos, x.generate())),
+        )
+        .chain(
+            context
+                .union_types()
+                .iter()
+                .map(|x| (x.doc.name.pos, x.generate())),
+        )
+        .chain(
+            context
+                .interface_types()
+                .iter()
+                .map(|x| (x.doc.name.pos, x.generate())),
+        )
+        .chain(
+            context
+                .input_types()
+                .iter()
+                .map(|x| (x.doc.name.pos, x.generate())),
+        )
+        .filter_map(|(pos, result)| result.err().map(|e| (pos, e)))
+        .map(|(pos, e)| located_diagnostic(&schema_file, pos, e))
         .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(GenericErrors::DiagnosticsError(errors));
+    }
 
-    let object_result = context
-        .object_types()
-        .iter()
-        .map(|x| x.generate())
-        .collect::<Vec<_>>();
+    let interfaces = context.interface_types();
 
-    let union_result = context
-        .union_types()
-        .iter()
-        .map(|x| x.generate())
-        .collect::<Vec<_>>();
+    context.generate_services()?;
 
-    let interfaces_result = context
-        .interface_types()
-        .iter()
-        .map(|x| x.generate())
-        .collect::<Vec<_>>();
+    let main_file = context.main_file().generate(
+        interfaces,
+        context.has_mutation(),
+        context.has_subscription(),
+        config.server(),
+        &config.cors_headers(),
+        config.auth().is_some(),
+    )?;
+    context.write_main_file(&main_file)?;
 
-    /*
-    let input_result = context
-        .input_types()
-        .iter()
-        .map(|x| x.generate())
-        .collect::<Vec<_>>();
-        */
+    // The Cargo.toml is generated last, its dependencies are derived from what the generated code
+    // uses.
+    context.generate_cargo_toml()?;
 
-    let interfaces = context.interface_types();
+    context.finalize()?;
 
-    context.generate_services()?;
+    Ok(())
+}
 
-    let _ = context.main_file().generate(interfaces);
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
 
-    println!("|------------------------------|");
-    println!("|           Result             |");
-    println!("|------------------------------|");
-    println!("Enum: {:?}", &enum_result);
-    println!("Objects: {:?}", &object_result);
-    println!("Unions: {:?}", &union_result);
-    println!("Interfaces: {:?}", &interfaces_result);
-    // println!("Inputs: {:?}", &input_result);
-    Ok(())
+    /// Generate a project from a schema and a config inside a temporary directory named after the
+    /// test, a previous run is removed first.
+    fn generate_project(name: &str, schema: &str, config: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("asbru-snapshot-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let schema_path = directory.join("schema.graphql");
+        let config_path = directory.join("config.toml");
+        let output = directory.join("output");
+        fs::write(&schema_path, schema).unwrap();
+        fs::write(&config_path, config).unwrap();
+
+        if let Err(e) = generate(&schema_path, &output, &config_path) {
+            match e {
+                GenericErrors::DiagnosticsError(diagnostics) => panic!(
+                    "{}",
+                    diagnostics
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+                e => panic!("{}", e),
+            }
+        }
+
+        output
+    }
+
+    /// Content of a file of a generated project.
+    fn generated(project: &Path, path: &str) -> String {
+        fs::read_to_string(project.join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_node_loader() {
+        let project = generate_project(
+            "node_loader",
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Pet @serviceBackedNode(service: "pets", methodName: "getPets") {
+  id: ID!
+  name: String!
+  friend: Pet
+  friends: [Pet!]!
+}
+"#,
+            r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+
+[services.pets.transport.info.method.getPets]
+route = "pets"
+http_method = "POST"
+body_args = ["ids"]
+"#,
+        );
+
+        insta::assert_snapshot!(
+            "node_loader",
+            generated(&project, "src/infrastructure/loader/pet.rs")
+        );
+        insta::assert_snapshot!(
+            "node_loader_resolvers",
+            generated(&project, "src/application/pet.rs")
+        );
+    }
+
+    #[test]
+    fn test_connection() {
+        let project = generate_project(
+            "connection",
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Pet {
+  id: ID!
+  name: String!
+  friends(first: Int, after: String): PetConnection!
+}
+
+type PetConnection
+  @serviceBackedConnection(service: "pets", methodName: "getFriends", sourceIdRequestFieldName: "petId")
+{
+  pageInfo: PageInfo!
+  edges: [PetEdge]
+  totalCount: Int!
+}
+
+type PetEdge {
+  cursor: String!
+  node: Pet!
+}
+
+type PageInfo {
+  hasPreviousPage: Boolean!
+  hasNextPage: Boolean!
+}
+"#,
+            r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+
+[services.pets.transport.info.method.getFriends]
+route = "pets/{petId}/friends"
+http_method = "GET"
+query_args = ["first", "after"]
+"#,
+        );
+
+        insta::assert_snapshot!("connection", generated(&project, "src/application/pet.rs"));
+    }
+
+    #[test]
+    fn test_mutation() {
+        let project = generate_project(
+            "mutation",
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Mutation {
+  placeOrder(input: PlaceOrderInput!): PlaceOrderPayload
+    @serviceBackedMutation(service: "pets", methodName: "placeOrder")
+}
+
+type Pet {
+  id: ID!
+}
+
+input PlaceOrderInput {
+  petId: ID!
+  quantity: Int!
+}
+
+type PlaceOrderPayload {
+  id: ID!
+  quantity: Int!
+}
+"#,
+            r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+
+[services.pets.transport.info.method.placeOrder]
+route = "store/order"
+http_method = "POST"
+body_args = ["petId", "quantity"]
+"#,
+        );
+
+        insta::assert_snapshot!(
+            "mutation",
+            generated(&project, "src/application/mutation.rs")
+        );
+    }
+
+    #[test]
+    fn test_inputs() {
+        let project = generate_project(
+            "inputs",
+            r#"type Query {
+  pets(filter: PetFilter!): [Pet!]! @serviceBackedQuery(service: "pets", methodName: "findPets")
+}
+
+type Pet {
+  id: ID!
+  status: PetStatus!
+}
+
+enum PetStatus {
+  AVAILABLE
+  SOLD
+}
+
+"Filter of a pet search"
+input PetFilter {
+  "Name of the pet"
+  name: String
+  status: PetStatus = AVAILABLE
+  tags: [String!]
+  limit: Int = 10
+  owner: OwnerFilter
+}
+
+input OwnerFilter {
+  id: ID!
+}
+"#,
+            r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.findPets]
+route = "pets/search"
+http_method = "POST"
+body_args = ["filter"]
+"#,
+        );
+
+        insta::assert_snapshot!("inputs", generated(&project, "src/domain/petfilter.rs"));
+        insta::assert_snapshot!(
+            "inputs_resolvers",
+            generated(&project, "src/application/query.rs")
+        );
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let project = generate_project(
+            "subscriptions",
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Subscription {
+  petUpdated(id: ID!): Pet @serviceBackedSubscription(service: "pets", methodName: "watchPet")
+  petEvents(id: ID!): Pet @serviceBackedSubscription(service: "pets", methodName: "petEvents")
+}
+
+type Pet {
+  id: ID!
+  name: String!
+}
+"#,
+            r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+
+[services.pets.transport.info.method.watchPet]
+route = "pets/{id}"
+http_method = "GET"
+stream = { type = "Polling", interval_ms = 5000 }
+
+[services.pets.transport.info.method.petEvents]
+route = "pets/{id}/events"
+http_method = "GET"
+stream = { type = "SSE" }
+"#,
+        );
+
+        insta::assert_snapshot!(
+            "subscriptions",
+            generated(&project, "src/application/subscription.rs")
+        );
+        insta::assert_snapshot!(
+            "subscriptions_streams",
+            generated(&project, "src/infrastructure/pets.rs")
+        );
+    }
+
+    #[test]
+    fn test_grpc() {
+        // The `.proto` is read from outside of the generated project.
+        let proto = std::env::temp_dir().join("asbru-snapshot-grpc-proto/friends.proto");
+        fs::create_dir_all(proto.parent().unwrap()).unwrap();
+        fs::write(
+            &proto,
+            r#"syntax = "proto3";
+
+package friends;
+
+service FriendService {
+  rpc GetFriend (GetFriendRequest) returns (Friend);
+}
+
+message GetFriendRequest {
+  string id = 1;
+}
+
+message Friend {
+  string id = 1;
+  string name = 2;
+}
+"#,
+        )
+        .unwrap();
+
+        let project = generate_project(
+            "grpc",
+            r#"type Query {
+  friend(id: ID!): Friend @serviceBackedQuery(service: "friends", methodName: "friendById")
+}
+
+type Friend {
+  id: ID!
+  name: String!
+}
+"#,
+            &format!(
+                r#"
+[services.friends.transport]
+type = "GRPC"
+
+[services.friends.transport.info]
+endpoint = "http://[::1]:50051"
+proto = "{}"
+package = "friends"
+service = "FriendService"
+
+[services.friends.transport.info.method.friendById]
+rpc = "GetFriend"
+request = "GetFriendRequest"
+request_args = ["id"]
+"#,
+                proto.display()
+            ),
+        );
+
+        insta::assert_snapshot!("grpc", generated(&project, "src/infrastructure/friends.rs"));
+        insta::assert_snapshot!("grpc_build", generated(&project, "build.rs"));
+        insta::assert_snapshot!(
+            "grpc_resolvers",
+            generated(&project, "src/application/query.rs")
+        );
+    }
+
+    #[test]
+    fn test_derived_field() {
+        let project = generate_project(
+            "derived_field",
+            r#"type Query {
+  user(id: ID!): User @serviceBackedQuery(service: "users", methodName: "getUser")
+}
+
+type User {
+  id: ID!
+  listingsCount: Int!
+  isHost: Boolean! @derivedField(provider: "crate::derived::is_host")
+  hasListings(min: Int!): Boolean! @derivedField(provider: "crate::derived::has_listings")
+}
+"#,
+            r#"
+[services.users.transport]
+type = "HTTP"
+
+[services.users.transport.info]
+endpoint = "http://users.io"
+
+[services.users.transport.info.method.getUser]
+route = "users/{id}"
+http_method = "GET"
+"#,
+        );
+
+        insta::assert_snapshot!(
+            "derived_field",
+            generated(&project, "src/application/user.rs")
+        );
+        insta::assert_snapshot!(
+            "derived_field_domain",
+            generated(&project, "src/domain/user.rs")
+        );
+    }
+
+    #[test]
+    fn test_interface_inheritance() {
+        let project = generate_project(
+            "interface_inheritance",
+            r#"type Query {
+  listing(id: ID!): Listing @serviceBackedQuery(service: "listings", methodName: "getListing")
+}
+
+interface Node {
+  id: ID!
+}
+
+interface Resource implements Node {
+  id: ID!
+  url: String!
+}
+
+type Listing implements Resource & Node {
+  id: ID!
+  url: String!
+  name: String!
+}
+"#,
+            r#"
+[services.listings.transport]
+type = "HTTP"
+
+[services.listings.transport.info]
+endpoint = "http://listings.io"
+
+[services.listings.transport.info.method.getListing]
+route = "listings/{id}"
+http_method = "GET"
+"#,
+        );
+
+        insta::assert_snapshot!(
+            "interface_inheritance_node",
+            generated(&project, "src/domain/node.rs")
+        );
+        insta::assert_snapshot!(
+            "interface_inheritance_resource",
+            generated(&project, "src/domain/resource.rs")
+        );
+    }
+
+    #[test]
+    fn test_package_lib() {
+        let project = generate_project(
+            "package_lib",
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Pet {
+  id: ID!
+}
+"#,
+            r#"
+[package]
+name = "pets-api"
+version = "0.2.0"
+authors = ["Jane Doe <jane@doe.dev>"]
+edition = "2018"
+license = "MIT"
+description = "The pets API"
+workspace = true
+
+[package.dependencies]
+tracing = "0.1"
+
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+"#,
+        );
+
+        insta::assert_snapshot!("package_lib_cargo", generated(&project, "Cargo.toml"));
+        insta::assert_snapshot!("package_lib", generated(&project, "src/lib.rs"));
+        assert!(!project.join("src/main.rs").exists());
+    }
+
+    #[test]
+    fn test_header_policy() {
+        let project = generate_project(
+            "header_policy",
+            r#"type Query {
+  pets(ownerId: ID!): [Pet!]! @serviceBackedQuery(service: "pets", methodName: "getPets")
+}
+
+type Pet {
+  id: ID!
+}
+"#,
+            r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.headers]
+forward = ["authorization", "x-tenant-id"]
+env = { "x-api-key" = "PETS_API_KEY" }
+args = { "x-owner-id" = "ownerId" }
+
+[services.pets.transport.info.method.getPets]
+route = "pets"
+http_method = "GET"
+"#,
+        );
+
+        insta::assert_snapshot!(
+            "header_policy",
+            generated(&project, "src/infrastructure/pets.rs")
+        );
+        insta::assert_snapshot!(
+            "header_policy_resolvers",
+            generated(&project, "src/application/query.rs")
+        );
+        insta::assert_snapshot!("header_policy_server", generated(&project, "src/main.rs"));
+    }
+
+    #[test]
+    fn test_servers() {
+        for framework in &["warp", "axum", "actix-web"] {
+            let name = format!("server_{}", framework.replace('-', "_"));
+            let project = generate_project(
+                &name,
+                r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Pet {
+  id: ID!
+}
+"#,
+                &format!(
+                    r#"
+[server]
+framework = "{}"
+bind = "127.0.0.1:4000"
+path = "/api/graphql"
+cors_origins = ["https://app.example.com"]
+timeout_ms = 5000
+body_limit = 1048576
+
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{{id}}"
+http_method = "GET"
+"#,
+                    framework
+                ),
+            );
+
+            insta::assert_snapshot!(name.as_str(), generated(&project, "src/main.rs"));
+            insta::assert_snapshot!(
+                format!("{}_cargo", name).as_str(),
+                generated(&project, "Cargo.toml")
+            );
+        }
+    }
+
+    #[test]
+    fn test_guards() {
+        let project = generate_project(
+            "guards",
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet") @scope(scopes: ["pets:read"])
+}
+
+type Subscription {
+  petUpdated(id: ID!): Pet
+    @serviceBackedSubscription(service: "pets", methodName: "watchPet")
+    @auth(requires: ["pets:read"])
+}
+
+type Pet @auth(requires: []) {
+  id: ID!
+  owner: String @scope(scopes: ["owners:read"])
+}
+"#,
+            r#"
+[auth]
+key = { type = "Secret", env = "JWT_SECRET" }
+issuer = "https://auth.example.com/"
+audience = "pets-api"
+
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+
+[services.pets.transport.info.method.watchPet]
+route = "pets/{id}"
+http_method = "GET"
+stream = { type = "Polling", interval_ms = 5000 }
+"#,
+        );
+
+        insta::assert_snapshot!(
+            "guards_query",
+            generated(&project, "src/application/query.rs")
+        );
+        insta::assert_snapshot!("guards_pet", generated(&project, "src/application/pet.rs"));
+        insta::assert_snapshot!(
+            "guards_subscription",
+            generated(&project, "src/application/subscription.rs")
+        );
+        insta::assert_snapshot!(
+            "guards_auth",
+            generated(&project, "src/infrastructure/auth.rs")
+        );
+        insta::assert_snapshot!("guards_server", generated(&project, "src/main.rs"));
+    }
 }
@@ -1,4 +1,6 @@
 pub mod config;
 pub mod context;
+pub mod diagnostic;
 pub mod generate;
 pub mod render;
+pub mod validate;
@@ -0,0 +1,43 @@
+use codegen::{Function, Scope};
+
+/// Generate the content of a build.rs file compiling the `.proto` files of gRPC services with
+/// `tonic-build`. Messages of the service `packages` derive serde traits so they can be mapped
+/// from and into domain structs, types outside of them, like well-known types, can't.
+pub fn generate_build_rs(protos: &[String], packages: &[String]) -> String {
+    let mut scope = Scope::new();
+
+    let protos = protos
+        .iter()
+        .map(|x| format!("\"{}\"", x))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let attributes = packages
+        .iter()
+        .map(|x| {
+            format!(
+                "\n        .type_attribute(\".{}\", \"#[derive(serde::Serialize, serde::Deserialize)]\")",
+                x
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("");
+
+    let mut main_function = Function::new("main");
+    main_function
+        .ret("Result<(), Box<dyn std::error::Error>>")
+        .line(format!(
+            r#"
+    tonic_build::configure()
+        .build_server(false){attributes}
+        .compile(&[{protos}], &["proto"])?;
+
+    Ok(())
+            "#,
+            attributes = attributes,
+            protos = protos
+        ));
+    scope.push_fn(main_function);
+
+    scope.to_string()
+}
@@ -1,5 +1,3 @@
-use std::fs;
-use std::io::Write;
 use std::{
     cell::{RefCell, RefMut},
     path::{Path, PathBuf},
@@ -8,22 +6,33 @@ use std::{
 
 use codegen::{Function, Scope};
 
+use super::server::generate_server;
+use crate::codegen::config::Server;
 use crate::codegen::context::auto_import::AutoImport;
+use crate::codegen::context::regions;
 use crate::codegen::render::graphql::interfaces::InterfaceWrapper;
 
-/// Structure to manage the main.rs generated file
+/// Structure to manage the main.rs generated file, or the lib.rs of a library crate.
 pub struct MainFile {
     path: PathBuf,
+    /// A library crate exposes its modules and its schema, without server.
+    lib: bool,
     scope: Rc<RefCell<Scope>>,
     main_function: Rc<RefCell<Function>>,
+    /// Data added to the Schema, like DataLoaders
+    schema_data: Rc<RefCell<Vec<String>>>,
+    /// Services whose client is built when the Schema is built.
+    clients: Rc<RefCell<Vec<String>>>,
 }
 
 impl MainFile {
-    pub fn new<P: AsRef<Path>>(path: &P) -> Self {
-        let main_scope = Rc::new(RefCell::new(Scope::new()));
+    pub fn new<P: AsRef<Path>>(path: &P, lib: bool) -> Self {
+        let mut scope = Scope::new();
+        // Hand-written modules, like the providers of derived fields.
+        scope.raw(&regions::user_region("modules", ""));
+        let main_scope = Rc::new(RefCell::new(scope));
         let main_function = Function::new("main")
             .set_async(true)
-            .attr("tokio::main(flavor = \"multi_thread\")")
             .ret("anyhow::Result<()>")
             .doc("Asbru auto-generated project")
             .clone();
@@ -31,11 +40,19 @@ impl MainFile {
         let path = path.as_ref().to_owned();
         MainFile {
             path,
+            lib,
             scope: main_scope,
             main_function: Rc::new(RefCell::new(main_function)),
+            schema_data: Rc::new(RefCell::new(Vec::new())),
+            clients: Rc::new(RefCell::new(Vec::new())),
         }
     }
 
+    /// Path of the file, `src/main.rs` or `src/lib.rs` inside the generated project.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Builder arround the main file
     pub fn main_scope(&self) -> RefMut<'_, Scope> {
         self.scope.borrow_mut()
@@ -46,21 +63,60 @@ impl MainFile {
         self.main_function.borrow_mut()
     }
 
-    /// Finalize the main file
+    /// Declare a module of the crate, public inside a library.
+    pub fn module(&self, name: &str) {
+        let module = if self.lib {
+            format!("pub mod {};", name)
+        } else {
+            format!("mod {};", name)
+        };
+
+        let mut scope = self.main_scope();
+        if !scope.to_string().lines().any(|x| x == module) {
+            scope.raw(&module);
+        }
+    }
+
+    /// Add data to the generated Schema, the data must be imported inside the main scope.
+    pub fn schema_data<S: AsRef<str>>(&self, data: S) {
+        self.schema_data
+            .borrow_mut()
+            .push(data.as_ref().to_string());
+    }
+
+    /// Build the client of a service with the Schema and add it to the Schema data. The client is
+    /// bound to `{service}_client` so other data, like DataLoaders, can share it.
+    pub fn service_client(&self, service_name: &str) {
+        self.main_scope().import(
+            &format!("infrastructure::{}", service_name),
+            &format!("{}_client", service_name),
+        );
+        self.clients.borrow_mut().push(service_name.to_string());
+    }
+
+    /// Finalize the main file, a library has no main function.
     pub fn finalize(&self) -> String {
         let mut scope = self.scope.borrow_mut();
-        scope.push_fn(self.main_function.borrow_mut().clone());
+        if !self.lib {
+            scope.push_fn(self.main_function.borrow_mut().clone());
+        }
 
         scope.to_string()
     }
 }
 
 impl MainFile {
-    // TODO: Use a shared config
+    /// Generate the content of the file: the schema and, unless it's a library, the server
+    /// serving it. The file is written by the `Context`, like any other generated file.
     pub fn generate(
         &self,
         interfaces: Vec<InterfaceWrapper>,
-    ) -> Result<(), crate::codegen::generate::GenericErrors> {
+        mutation: bool,
+        subscription: bool,
+        server: &Server,
+        cors_headers: &[String],
+        auth: bool,
+    ) -> Result<String, crate::codegen::generate::GenericErrors> {
         let interfaces = interfaces
             .iter()
             .map(|x| {
@@ -71,69 +127,82 @@ impl MainFile {
             .collect::<Vec<String>>()
             .join("");
 
-        let output = &self.path;
-        self.main_scope().import("async_graphql", "Schema");
-        self.main_scope().import("async_graphql", "EmptyMutation");
-        self.main_scope()
-            .import("async_graphql", "EmptySubscription");
-        self.main_scope().import("warp", "Filter");
-        self.main_scope().import("std", "env");
-        self.main_scope().import("tower::make", "Shared");
-        self.main_scope().import("tower", "ServiceBuilder");
+        let clients = self
+            .clients
+            .borrow()
+            .iter()
+            .map(|x| format!("let {service}_client = {service}_client()?;\n", service = x))
+            .collect::<String>();
 
-        self.main_scope().import("domain::query", "Query");
-        self.main_function().line(format!(
-            r#"
-    let schema = Schema::build(Query::default(), EmptyMutation, EmptySubscription){interfaces}.finish();
-
-    let env_port = env::var("PORT")
-        .expect("No PORT provided in env variables.");
-    let env_port: u16 = env::var("PORT")
-        .expect("No PORT provided in env variables.").parse::<u16>().expect("No valid PORT provided.");
-
-    let cors = warp::cors()
-        .allow_methods(vec!["POST"])
-        .allow_header("content-type")
-        .allow_any_origin()
-        .build();
-
-    let graphql_post = warp::post()
-        .and(warp::path("graphql"))
-        .and(async_graphql_warp::graphql(schema))
-        .and_then(
-            |(schema, request): (
-                Schema<Query, EmptyMutation, EmptySubscription>,
-                async_graphql::Request,
-            )| async move {{
-                Ok::<_, std::convert::Infallible>(async_graphql_warp::Response::from(
-                    schema
-                        .execute(request)
-                        .await,
-                ))
-            }},
-        );
+        let data = self
+            .clients
+            .borrow()
+            .iter()
+            .map(|x| format!("{}_client.clone()", x))
+            .chain(self.schema_data.borrow().iter().cloned())
+            .map(|x| format!(".data({})", x))
+            .collect::<Vec<String>>()
+            .join("");
 
-    let filters = graphql_post
-            .with(cors)
-            .with(warp::trace::request());
+        let (mutation, mutation_builder) = if mutation {
+            self.main_scope().import("domain::mutation", "Mutation");
+            ("Mutation", "Mutation::default()")
+        } else {
+            self.main_scope().import("async_graphql", "EmptyMutation");
+            ("EmptyMutation", "EmptyMutation")
+        };
+
+        let (subscription, subscription_builder) = if subscription {
+            self.main_scope()
+                .import("domain::subscription", "Subscription");
+            ("Subscription", "Subscription::default()")
+        } else {
+            self.main_scope()
+                .import("async_graphql", "EmptySubscription");
+            ("EmptySubscription", "EmptySubscription")
+        };
 
-    let service = ServiceBuilder::new().timeout(std::time::Duration::from_secs(10)).service(warp::service(filters));
+        self.main_scope().import("async_graphql", "Schema");
 
-    let service = Shared::new(service);
+        self.main_scope().import("domain::query", "Query");
+        self.main_scope().raw(&format!(
+            "/// Schema of the generated API.\n{}type AppSchema = Schema<Query, {}, {}>;",
+            if self.lib { "pub " } else { "" },
+            mutation,
+            subscription
+        ));
 
-    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], env_port));
-    let listener = std::net::TcpListener::bind(addr).unwrap();
+        let mut schema_function = Function::new("schema");
+        schema_function
+            .doc("Build the schema of the generated API, with its data and the clients of the services.")
+            .ret("anyhow::Result<AppSchema>")
+            .line(format!(
+                "{clients}Ok(Schema::build(Query::default(), {mutation_builder}, {subscription_builder}){interfaces}{data}.finish())",
+                clients = clients,
+                mutation_builder = mutation_builder,
+                subscription_builder = subscription_builder,
+                interfaces = interfaces,
+                data = data,
+            ));
+        if self.lib {
+            schema_function.vis("pub");
+        }
+        self.main_scope().push_fn(schema_function);
 
-    warp::hyper::Server::from_tcp(listener).unwrap().serve(service).await?;
+        if self.lib {
+            return Ok(self.finalize());
+        }
 
-    Ok(())
-        "#,
-        interfaces = interfaces
-        ));
-        let content = self.finalize();
+        self.main_function().line("let schema = schema()?;");
+        generate_server(
+            server,
+            cors_headers,
+            subscription,
+            auth,
+            &mut self.main_scope(),
+            &mut self.main_function(),
+        );
 
-        let mut f = fs::File::create(&output)?;
-        f.write_all(&(content.as_bytes()))?;
-        Ok(())
+        Ok(self.finalize())
     }
 }
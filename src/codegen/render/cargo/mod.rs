@@ -1,11 +1,14 @@
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::fs;
-use std::io::Write;
-use std::{collections::HashMap, path::Path};
+use std::collections::BTreeMap;
 use toml;
 
+use crate::codegen::config::{Framework, Package, ScalarDependency};
+
+mod build;
 mod main;
+mod server;
+pub use build::generate_build_rs;
 pub use main::MainFile;
 
 // TODO: Builder pattern for Cargo.
@@ -34,44 +37,128 @@ struct Cargo {
     package: PackageConfig,
     bin: Option<Vec<BinConfig>>,
     #[serde(serialize_with = "toml::ser::tables_last")]
-    dependencies: HashMap<String, serde_json::Value>,
+    dependencies: BTreeMap<String, serde_json::Value>,
+    #[serde(
+        rename = "build-dependencies",
+        skip_serializing_if = "BTreeMap::is_empty",
+        serialize_with = "toml::ser::tables_last"
+    )]
+    build_dependencies: BTreeMap<String, serde_json::Value>,
+}
+
+/// What the generated code uses, the dependencies of the project are derived from it.
+#[derive(Debug, Default)]
+pub struct ProjectFeatures {
+    /// Services called over HTTP with `reqwest`.
+    pub http: bool,
+    /// Services called over gRPC with a `tonic` client compiled by the `build.rs`.
+    pub grpc: bool,
+    /// Server-Sent Events streams, read with `async-stream`.
+    pub sse: bool,
+    /// DataLoaders of the nodes, implemented with `async-trait`.
+    pub loaders: bool,
+    /// Authentication of the callers with `jsonwebtoken`, its guards use `async-trait`.
+    pub auth: bool,
+    /// A library crate exposing the schema, without server.
+    pub lib: bool,
+    /// Framework of the server, unless it's a library.
+    pub framework: Framework,
+    /// Crates of the Rust types of the custom scalars.
+    pub scalars: Vec<ScalarDependency>,
+}
+
+/// Dependencies of the server, with the `async-graphql` integration of its framework.
+fn server_dependencies(
+    framework: Framework,
+    dependencies: &mut BTreeMap<String, serde_json::Value>,
+) {
+    let tower = json!({
+        "version": "0.4.0",
+        "features": ["full"],
+    });
+
+    match framework {
+        Framework::Warp => {
+            dependencies.insert("async-graphql-warp".to_string(), json!("2.9.9"));
+            dependencies.insert("warp".to_string(), json!("0.3.0"));
+            dependencies.insert("tower".to_string(), tower);
+        }
+        Framework::Axum => {
+            dependencies.insert("async-graphql-axum".to_string(), json!("2.9.9"));
+            dependencies.insert(
+                "axum".to_string(),
+                json!({
+                    "version": "0.2.*",
+                    "features": ["ws", "headers"],
+                }),
+            );
+            dependencies.insert("tower".to_string(), tower);
+            dependencies.insert(
+                "tower-http".to_string(),
+                json!({
+                    "version": "0.1.*",
+                    "features": ["cors"],
+                }),
+            );
+        }
+        Framework::ActixWeb => {
+            dependencies.insert("async-graphql-actix-web".to_string(), json!("2.9.9"));
+            dependencies.insert("actix-web".to_string(), json!("4.0.0-beta.8"));
+            dependencies.insert("actix-cors".to_string(), json!("0.6.0-beta.2"));
+        }
+    }
 }
 
-/// Generate a Cargo toml file
-/// @test-only, should create a builder pattern for a Cargo struct and a save
-pub fn generate_cargo_toml<P: AsRef<Path>>(path: P) -> () {
-    let package = PackageConfig {
-        name: "asbru-test".to_string(),
-        version: "0.1.0".to_string(),
-        authors: vec!["Anthony Griffon <anthony@griffon.one>".to_string()],
-        description: Some("A little description".to_string()),
+/// Generate the content of the Cargo.toml of the project, named `name`.
+///
+/// Dependencies are derived from what the generated code uses, the dependencies of the package
+/// config are added and take precedence.
+pub fn generate_cargo_toml(package: &Package, name: &str, project: &ProjectFeatures) -> String {
+    let package_config = PackageConfig {
+        name: name.to_string(),
+        version: package.version.clone(),
+        authors: package.authors.clone(),
+        description: package.description.clone(),
         readme: None,
         documentation: None,
         repository: None,
-        license: None,
-        edition: Some("2018".to_string()),
+        license: package.license.clone(),
+        edition: Some(package.edition.clone()),
     };
 
-    let bin = BinConfig {
-        name: "asbru-test".to_string(),
+    // A library is found at `src/lib.rs` by Cargo.
+    let bin = Some(vec![BinConfig {
+        name: name.to_string(),
         path: "src/main.rs".to_string(),
-    };
+    }])
+    .filter(|_| !project.lib);
+
+    let mut async_graphql_features = vec!["url", "chrono", "apollo_tracing", "unblock", "tracing"];
+    for feature in project
+        .scalars
+        .iter()
+        .filter_map(|x| x.async_graphql_feature.as_deref())
+    {
+        if !async_graphql_features.contains(&feature) {
+            async_graphql_features.push(feature);
+        }
+    }
 
-    let mut dependencies: HashMap<String, serde_json::Value> = HashMap::new();
+    let mut dependencies: BTreeMap<String, serde_json::Value> = BTreeMap::new();
     dependencies.insert(
         "async-graphql".to_string(),
         json!({
             "version": "2.9.9",
-            "features": ["url", "chrono", "apollo_tracing", "unblock", "tracing"]
+            "features": async_graphql_features
         }),
     );
-    dependencies.insert("async-graphql-warp".to_string(), json!("2.9.9"));
-    dependencies.insert("async-graphql-derive".to_string(), json!("2.9.9"));
-    dependencies.insert("async-graphql-parser".to_string(), json!("2.9.9"));
 
-    dependencies.insert("warp".to_string(), json!("0.3.0"));
+    if !project.lib {
+        server_dependencies(project.framework, &mut dependencies);
+    }
 
     dependencies.insert("anyhow".to_string(), json!("1.0.*"));
+    dependencies.insert("futures".to_string(), json!("0.3.*"));
     dependencies.insert(
         "tokio".to_string(),
         json!({
@@ -82,31 +169,57 @@ pub fn generate_cargo_toml<P: AsRef<Path>>(path: P) -> () {
     dependencies.insert("serde_derive".to_string(), json!("1.0.*"));
     dependencies.insert("serde".to_string(), json!("1.0.*"));
     dependencies.insert("serde_json".to_string(), json!("1.0.*"));
-    dependencies.insert(
-        "tower".to_string(),
-        json!({
-            "version": "0.4.0",
-            "features": ["full"],
-        }),
-    );
-    dependencies.insert(
-        "reqwest".to_string(),
-        json!({
-            "version": "0.11.*",
-            "features": ["json"],
-        }),
+
+    if project.loaders || project.auth {
+        dependencies.insert("async-trait".to_string(), json!("0.1.*"));
+    }
+
+    if project.auth {
+        dependencies.insert("jsonwebtoken".to_string(), json!("8.*"));
+        dependencies.insert("once_cell".to_string(), json!("1.*"));
+    }
+
+    if project.http {
+        dependencies.insert(
+            "reqwest".to_string(),
+            json!({
+                "version": "0.11.*",
+                "features": ["json"],
+            }),
+        );
+        dependencies.insert("percent-encoding".to_string(), json!("2.*"));
+    }
+
+    if project.sse {
+        dependencies.insert("async-stream".to_string(), json!("0.3.*"));
+    }
+
+    let mut build_dependencies: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+    if project.grpc {
+        dependencies.insert("tonic".to_string(), json!("0.8.*"));
+        dependencies.insert("prost".to_string(), json!("0.11.*"));
+        build_dependencies.insert("tonic-build".to_string(), json!("0.8.*"));
+    }
+
+    for scalar in project.scalars.iter() {
+        dependencies
+            .entry(scalar.name.clone())
+            .or_insert_with(|| scalar.version.clone());
+    }
+
+    dependencies.extend(
+        package
+            .dependencies
+            .iter()
+            .map(|(name, version)| (name.clone(), version.clone())),
     );
 
     let cargo = Cargo {
-        package,
-        bin: Some(vec![bin]),
+        package: package_config,
+        bin,
         dependencies,
+        build_dependencies,
     };
 
-    let encoded = toml::to_string(&cargo).unwrap();
-
-    let path = path.as_ref();
-    let mut f = fs::File::create(&path).unwrap();
-    f.write_all(encoded.as_bytes()).unwrap();
-    println!("Processing {:?}", &path);
+    toml::to_string(&cargo).unwrap()
 }
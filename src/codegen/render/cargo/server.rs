@@ -0,0 +1,479 @@
+use codegen::{Function, Scope};
+
+use crate::codegen::config::{Framework, Server};
+
+/// Generate the server serving the schema built by `schema()`, with the framework of the server
+/// config: its handlers are pushed inside the scope and the main function serves them.
+/// With `auth`, the authentication of each request is added to its data, and the one of each
+/// websocket connection to the data of the connection.
+/// The CORS allow the `cors_headers`, lowercased.
+pub fn generate_server(
+    server: &Server,
+    cors_headers: &[String],
+    subscription: bool,
+    auth: bool,
+    scope: &mut Scope,
+    main_function: &mut Function,
+) {
+    let mut bind_address = Function::new("bind_address");
+    bind_address
+        .doc("Address the server listens on, its port is overridden by the `PORT` environment variable.")
+        .ret("anyhow::Result<std::net::SocketAddr>")
+        .line(format!(
+            r#"let mut address: std::net::SocketAddr = "{bind}".parse()?;
+if let Ok(port) = std::env::var("PORT") {{
+    address.set_port(port.parse()?);
+}}
+Ok(address)"#,
+            bind = server.bind
+        ));
+    scope.push_fn(bind_address);
+    scope.import("infrastructure::headers", "IncomingHeaders");
+    let request_data = if auth {
+        scope.import("infrastructure::auth", "Authentication");
+        ".data(Authentication::new(&headers)).data(headers)"
+    } else {
+        ".data(headers)"
+    };
+
+    // A websocket is authenticated once, when the connection is initialized.
+    if subscription {
+        let mut connection_data = Function::new("connection_data");
+        connection_data
+            .doc("Data of a websocket connection: the headers of the upgrade request, with the ones sent inside\nthe `connection_init` payload, like `{ \"authorization\": \"Bearer ...\" }`.")
+            .arg("headers", "IncomingHeaders")
+            .arg("connection_init", "serde_json::Value")
+            .ret("async_graphql::Data")
+            .line("let headers = headers.with_payload(&connection_init);")
+            .line("let mut data = async_graphql::Data::default();");
+        if auth {
+            connection_data.line("data.insert(Authentication::new(&headers));");
+        }
+        connection_data.line("data.insert(headers);").line("data");
+        scope.push_fn(connection_data);
+    }
+
+    match server.framework {
+        Framework::Warp => {
+            main_function.attr("tokio::main(flavor = \"multi_thread\")");
+            warp_server(
+                server,
+                cors_headers,
+                subscription,
+                request_data,
+                scope,
+                main_function,
+            );
+        }
+        Framework::Axum => {
+            main_function.attr("tokio::main(flavor = \"multi_thread\")");
+            axum_server(
+                server,
+                cors_headers,
+                subscription,
+                request_data,
+                scope,
+                main_function,
+            );
+        }
+        Framework::ActixWeb => {
+            main_function.attr("actix_web::main");
+            actix_web_server(
+                server,
+                cors_headers,
+                subscription,
+                request_data,
+                scope,
+                main_function,
+            );
+        }
+    }
+}
+
+/// Served with `warp` behind a `tower` timeout.
+fn warp_server(
+    server: &Server,
+    cors_headers: &[String],
+    subscription: bool,
+    request_data: &str,
+    scope: &mut Scope,
+    main_function: &mut Function,
+) {
+    scope.import("warp", "Filter");
+    scope.import("tower::make", "Shared");
+    scope.import("tower", "ServiceBuilder");
+
+    let path = server
+        .path_segments()
+        .iter()
+        .map(|x| format!(".and(warp::path(\"{}\"))", x))
+        .collect::<String>();
+
+    let origins = if server.cors_origins.is_empty() {
+        ".allow_any_origin()".to_string()
+    } else {
+        format!(
+            ".allow_origins(vec![{}])",
+            quoted(&server.cors_origins).join(", ")
+        )
+    };
+
+    let limit = server
+        .body_limit
+        .map(|x| format!("\n        .and(warp::body::content_length_limit({}))", x))
+        .unwrap_or_default();
+
+    // Subscriptions are served over websocket on the same path.
+    let (methods, subscription_route, subscription_filter) = if subscription {
+        (
+            r#""POST", "GET""#,
+            format!(
+                r#"
+    let subscription_schema = schema.clone();
+    let graphql_subscription = warp::any(){path}
+        .and(warp::path::end())
+        .and(warp::ws())
+        .and(async_graphql_warp::graphql_protocol())
+        .and(warp::header::headers_cloned())
+        .map(
+            move |websocket: warp::ws::Ws,
+                  protocol: async_graphql::http::WebSocketProtocols,
+                  headers: warp::http::HeaderMap| {{
+                let schema = subscription_schema.clone();
+                let headers = {incoming_headers};
+                let reply = websocket.on_upgrade(move |socket| {{
+                    async_graphql_warp::graphql_subscription_upgrade_with_data(
+                        socket,
+                        protocol,
+                        schema,
+                        move |connection_init| async move {{ Ok(connection_data(headers, connection_init)) }},
+                    )
+                }});
+                warp::reply::with_header(
+                    reply,
+                    "Sec-WebSocket-Protocol",
+                    protocol.sec_websocket_protocol(),
+                )
+            }},
+        );
+"#,
+                path = path,
+                incoming_headers = incoming_headers("headers")
+            ),
+            ".or(graphql_subscription)",
+        )
+    } else {
+        (r#""POST""#, String::new(), "")
+    };
+
+    main_function.line(format!(
+        r#"
+    let cors = warp::cors()
+        .allow_methods(vec![{methods}])
+        .allow_headers(vec![{headers}])
+        {origins}
+        .build();
+
+    let graphql_post = warp::post(){path}
+        .and(warp::path::end()){limit}
+        .and(async_graphql_warp::graphql(schema.clone()))
+        .and(warp::header::headers_cloned())
+        .and_then(
+            |(schema, request): (
+                AppSchema,
+                async_graphql::Request,
+            ), headers: warp::http::HeaderMap| async move {{
+                let headers = {incoming_headers};
+                Ok::<_, std::convert::Infallible>(async_graphql_warp::Response::from(
+                    schema
+                        .execute(request{request_data})
+                        .await,
+                ))
+            }},
+        );
+{subscription_route}
+    let filters = graphql_post{subscription_filter}
+            .with(cors)
+            .with(warp::trace::request());
+
+    let service = ServiceBuilder::new()
+        .timeout(std::time::Duration::from_millis({timeout}))
+        .service(warp::service(filters));
+
+    let service = Shared::new(service);
+
+    let listener = std::net::TcpListener::bind(bind_address()?)?;
+
+    warp::hyper::Server::from_tcp(listener)?.serve(service).await?;
+
+    Ok(())
+        "#,
+        methods = methods,
+        headers = quoted(cors_headers).join(", "),
+        origins = origins,
+        path = path,
+        limit = limit,
+        incoming_headers = incoming_headers("headers"),
+        request_data = request_data,
+        subscription_route = subscription_route,
+        subscription_filter = subscription_filter,
+        timeout = server.timeout_ms,
+    ));
+}
+
+/// Served with `axum`, CORS are handled by `tower-http`.
+fn axum_server(
+    server: &Server,
+    cors_headers: &[String],
+    subscription: bool,
+    request_data: &str,
+    scope: &mut Scope,
+    main_function: &mut Function,
+) {
+    let mut handler = Function::new("graphql_handler");
+    handler
+        .set_async(true)
+        .arg("schema", "axum::extract::Extension<AppSchema>")
+        .ret("async_graphql_axum::GraphQLResponse");
+    let request = match server.body_limit {
+        Some(limit) => {
+            handler.arg(
+                "request",
+                format!(
+                    "axum::extract::ContentLengthLimit<async_graphql_axum::GraphQLRequest, {}>",
+                    limit
+                ),
+            );
+            "request.0.into_inner()"
+        }
+        None => {
+            handler.arg("request", "async_graphql_axum::GraphQLRequest");
+            "request.into_inner()"
+        }
+    };
+    // The headers are extracted last, the request extractor reads them.
+    handler
+        .arg("headers", "axum::http::HeaderMap")
+        .line(format!("let headers = {};", incoming_headers("headers")))
+        .line(format!(
+            "schema.execute({}{}).await.into()",
+            request, request_data
+        ));
+    scope.push_fn(handler);
+
+    let methods = if subscription {
+        let mut handler = Function::new("graphql_subscription_handler");
+        handler
+            .set_async(true)
+            .arg("websocket", "axum::extract::ws::WebSocketUpgrade")
+            .arg("schema", "axum::extract::Extension<AppSchema>")
+            .arg("protocol", "async_graphql_axum::SecWebsocketProtocol")
+            .arg("headers", "axum::http::HeaderMap")
+            .ret("impl axum::response::IntoResponse")
+            .line(format!(
+                r#"let schema = schema.0;
+let headers = {incoming_headers};
+websocket
+    .protocols(async_graphql::http::ALL_WEBSOCKET_PROTOCOLS)
+    .on_upgrade(move |socket| {{
+        async_graphql_axum::graphql_subscription_with_data(
+            socket,
+            schema,
+            protocol,
+            move |connection_init| async move {{ Ok(connection_data(headers, connection_init)) }},
+        )
+    }})"#,
+                incoming_headers = incoming_headers("headers")
+            ));
+        scope.push_fn(handler);
+
+        (
+            "axum::http::Method::POST, axum::http::Method::GET",
+            "axum::handler::post(graphql_handler).get(graphql_subscription_handler)",
+        )
+    } else {
+        (
+            "axum::http::Method::POST",
+            "axum::handler::post(graphql_handler)",
+        )
+    };
+
+    let origins = if server.cors_origins.is_empty() {
+        "tower_http::cors::any()".to_string()
+    } else {
+        format!(
+            "tower_http::cors::Origin::list(vec![{}])",
+            quoted(&server.cors_origins)
+                .iter()
+                .map(|x| format!("{}.parse()?", x))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    };
+
+    main_function.line(format!(
+        r#"
+    let cors = tower_http::cors::CorsLayer::new()
+        .allow_methods(vec![{methods}])
+        .allow_headers(vec![{headers}])
+        .allow_origin({origins});
+
+    let app = axum::Router::new()
+        .route("{route}", {handlers})
+        .layer(axum::AddExtensionLayer::new(schema))
+        .layer(cors)
+        .layer(tower::timeout::TimeoutLayer::new(std::time::Duration::from_millis({timeout})))
+        .handle_error(|_: tower::BoxError| {{
+            Ok::<_, std::convert::Infallible>(axum::http::StatusCode::REQUEST_TIMEOUT)
+        }});
+
+    axum::Server::bind(&bind_address()?)
+        .serve(app.into_make_service())
+        .await?;
+
+    Ok(())
+        "#,
+        methods = methods.0,
+        handlers = methods.1,
+        headers = quoted(cors_headers)
+            .iter()
+            .map(|x| format!("axum::http::HeaderName::from_static({})", x))
+            .collect::<Vec<String>>()
+            .join(", "),
+        origins = origins,
+        route = server.route(),
+        timeout = server.timeout_ms,
+    ));
+}
+
+/// Served with `actix-web`, CORS are handled by `actix-cors`. Its client timeout only bounds the
+/// reading of the request, the execution of the request goes through a `tokio` timeout.
+fn actix_web_server(
+    server: &Server,
+    cors_headers: &[String],
+    subscription: bool,
+    request_data: &str,
+    scope: &mut Scope,
+    main_function: &mut Function,
+) {
+    let mut handler = Function::new("graphql_handler");
+    handler
+        .set_async(true)
+        .arg("schema", "actix_web::web::Data<AppSchema>")
+        .arg("request", "async_graphql_actix_web::Request")
+        .arg("http_request", "actix_web::HttpRequest")
+        .ret("actix_web::Result<async_graphql_actix_web::Response>")
+        .line(format!(
+            "let headers = {};",
+            incoming_headers("http_request.headers()")
+        ))
+        .line(format!(
+            r#"tokio::time::timeout(
+    std::time::Duration::from_millis({timeout}),
+    schema.execute(request.into_inner(){request_data}),
+)
+.await
+.map(Into::into)
+.map_err(|_| actix_web::error::ErrorRequestTimeout("Request timeout"))"#,
+            timeout = server.timeout_ms,
+            request_data = request_data,
+        ));
+    scope.push_fn(handler);
+
+    let (methods, subscription_route) = if subscription {
+        let mut handler = Function::new("graphql_subscription_handler");
+        handler
+            .set_async(true)
+            .arg("schema", "actix_web::web::Data<AppSchema>")
+            .arg("request", "actix_web::HttpRequest")
+            .arg("payload", "actix_web::web::Payload")
+            .ret("actix_web::Result<actix_web::HttpResponse>")
+            .line(format!(
+                "let headers = {};",
+                incoming_headers("request.headers()")
+            ))
+            .line(
+                r#"async_graphql_actix_web::WSSubscription::start_with_initializer(
+    AppSchema::clone(&*schema),
+    &request,
+    payload,
+    move |connection_init| async move { Ok(connection_data(headers, connection_init)) },
+)"#,
+            );
+        scope.push_fn(handler);
+
+        (
+            r#""POST", "GET""#,
+            r#"
+                    .route(
+                        actix_web::web::get()
+                            .guard(actix_web::guard::Header("upgrade", "websocket"))
+                            .to(graphql_subscription_handler),
+                    )"#,
+        )
+    } else {
+        (r#""POST""#, "")
+    };
+
+    let origins = if server.cors_origins.is_empty() {
+        ".allow_any_origin()".to_string()
+    } else {
+        quoted(&server.cors_origins)
+            .iter()
+            .map(|x| format!("\n            .allowed_origin({})", x))
+            .collect::<String>()
+    };
+
+    let limit = server
+        .body_limit
+        .map(|x| {
+            format!(
+                "\n            .app_data(actix_web::web::PayloadConfig::new({}))",
+                x
+            )
+        })
+        .unwrap_or_default();
+
+    main_function.line(format!(
+        r#"
+    let server = actix_web::HttpServer::new(move || {{
+        let cors = actix_cors::Cors::default()
+            .allowed_methods(vec![{methods}])
+            .allowed_headers(vec![{headers}])
+            {origins};
+
+        actix_web::App::new()
+            .app_data(actix_web::web::Data::new(schema.clone())){limit}
+            .wrap(cors)
+            .service(
+                actix_web::web::resource("{route}")
+                    .route(actix_web::web::post().to(graphql_handler)){subscription_route},
+            )
+    }})
+    .bind(bind_address()?)?;
+
+    server.run().await?;
+
+    Ok(())
+        "#,
+        methods = methods,
+        headers = quoted(cors_headers).join(", "),
+        origins = origins,
+        limit = limit,
+        route = server.route(),
+        subscription_route = subscription_route,
+    ));
+}
+
+/// The incoming headers of the request, from the `headers` of the framework. They are added to
+/// the request data so resolvers can forward them to the services.
+fn incoming_headers(headers: &str) -> String {
+    format!(
+        "IncomingHeaders::new({}.iter().map(|(name, value)| (name.as_str(), value.as_bytes())))",
+        headers
+    )
+}
+
+fn quoted(values: &[String]) -> Vec<String> {
+    values.iter().map(|x| format!("{:?}", x)).collect()
+}
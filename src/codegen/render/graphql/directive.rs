@@ -1,12 +1,361 @@
-use async_graphql_parser::types::FieldDefinition;
-use codegen::{Function, Scope};
+use async_graphql_parser::types::{
+    BaseType, ConstDirective, FieldDefinition, TypeDefinition, TypeKind,
+};
+use async_graphql_parser::Positioned;
+use async_graphql_value::ConstValue;
+use codegen::{Function, Impl, Scope, Struct};
 use convert_case::{Case, Casing};
 
-use crate::codegen::{context::Context, render::graphql::scalars::ToRustType};
+use crate::codegen::{
+    config::{client_type, json_pointer, ArgumentSource, ArgumentsMapping, BatchArgument, Method},
+    context::Context,
+    generate::GenericErrors,
+    render::graphql::scal::asbru_type::AsbruTypeErrors,
+    render::graphql::scalars::ToRustType,
+};
+
+/// GraphQL types of the arguments which can be sent as headers.
+const HEADER_TYPES: [&str; 5] = ["String", "ID", "Int", "Float", "Boolean"];
+
+/// Generate the call to a service method and import everything needed for it inside the scope.
+/// The arguments of the method are constructed from variables with the same name, so they must
+/// be available where the call is generated, with a `client` referencing the client of the
+/// service stored inside the schema data.
+///
+/// The generated call is not awaited.
+///
+/// With `stream`, the call is made to the streaming function of the method, which takes the
+/// ownership of the `client`: it must be the inner client of the service.
+///
+/// A HTTP method is also called with the `headers` bound by `headers_binding`.
+fn service_method_call<'a>(
+    context: &'a Context,
+    service: &str,
+    method_name: &str,
+    method_type: &str,
+    stream: bool,
+    scope: &mut Scope,
+) -> Result<String, GenericErrors> {
+    let main_name = format!("{}_{}", service, method_name);
+    let infrastructure_path = format!("crate::infrastructure::{}", service);
+    let (function_name, client) = if stream {
+        (
+            format!(
+                "{}_{}_method_stream",
+                service,
+                method_name.to_case(Case::Snake)
+            ),
+            "client",
+        )
+    } else {
+        (
+            format!("{}_{}_method", service, method_name.to_case(Case::Snake)),
+            "&client.0",
+        )
+    };
+    scope.import(&infrastructure_path, &function_name);
+    scope.import(&infrastructure_path, &client_type(service));
+
+    let method = context
+        .get_service_by_name(service)?
+        .get_a_method(service, method_name)?;
+
+    let arguments = method
+        .arguments_construct(&main_name.to_case(Case::Pascal))
+        .into_iter()
+        .map(|(name, construct)| {
+            scope.import(&infrastructure_path, &name);
+            format!(",\n        {}", construct)
+        })
+        .collect::<Vec<String>>()
+        .join("");
+
+    let headers = match (&method, stream) {
+        (Method::HTTP(_), false) => ",\n        &headers",
+        (Method::HTTP(_), true) => ",\n        headers",
+        (Method::GRPC(_), _) => "",
+    };
+
+    Ok(format!(
+        r#"{method}::<{method_type}>(
+        {client}{arguments}{headers}
+        )"#,
+        method = function_name,
+        client = client,
+        method_type = method_type,
+        arguments = arguments,
+        headers = headers
+    ))
+}
+
+/// Bind the `headers` sent to a HTTP service for a field: the incoming headers forwarded to the
+/// service and the headers computed from the arguments of the field. Only scalar arguments can
+/// be sent as headers, others are ignored.
+///
+/// The resolver must have a `ctx` argument, nothing is bound for a gRPC service.
+fn headers_binding<'a>(
+    context: &'a Context,
+    service: &str,
+    field: Option<&FieldDefinition>,
+    scope: &mut Scope,
+) -> Result<String, GenericErrors> {
+    let policy = match context.get_service_by_name(service)?.header_policy() {
+        Some(policy) => policy,
+        None => return Ok("".to_string()),
+    };
+
+    scope.import(
+        &format!("crate::infrastructure::{}", service),
+        &format!("{}_headers", service),
+    );
+    scope.import("crate::infrastructure::headers", "IncomingHeaders");
+
+    let arguments = policy
+        .args
+        .iter()
+        .filter_map(|(header, argument)| {
+            let argument = field?
+                .arguments
+                .iter()
+                .map(|x| &x.node)
+                .find(|x| x.name.node.as_str() == argument)?;
+            let is_scalar = match &argument.ty.node.base {
+                BaseType::Named(name) => HEADER_TYPES.contains(&name.as_str()),
+                BaseType::List(_) => false,
+            };
+            if !is_scalar {
+                return None;
+            }
+
+            // An `ID` is sent as its inner string.
+            let value = |name: &str| match &argument.ty.node.base {
+                BaseType::Named(ty) if ty.as_str() == "ID" => format!("{}.as_str()", name),
+                _ => format!("&{}", name),
+            };
+            let name = argument.name.node.as_str().to_case(Case::Snake);
+            Some(if argument.ty.node.nullable {
+                format!(
+                    "\n    if let Some(value) = &{} {{\n        headers.insert({:?}, {});\n    }}",
+                    name,
+                    header.to_lowercase(),
+                    value("value")
+                )
+            } else {
+                format!(
+                    "\n    headers.insert({:?}, {});",
+                    header.to_lowercase(),
+                    value(&name)
+                )
+            })
+        })
+        .collect::<String>();
+
+    Ok(format!(
+        "let {mutable}headers = {service}_headers(ctx.data_opt::<IncomingHeaders>());{arguments}",
+        mutable = if arguments.is_empty() { "" } else { "mut " },
+        service = service,
+        arguments = arguments
+    ))
+}
+
+/// Find the input object argument of the field which has a field with this snake case name.
+fn input_field_argument<'a>(
+    context: &'a Context,
+    field: &'a FieldDefinition,
+    name: &str,
+) -> Option<String> {
+    field
+        .arguments
+        .iter()
+        .find(|x| {
+            let gql_type = &x.node.ty.node;
+            !gql_type.nullable
+                && matches!(gql_type.base, BaseType::Named(_))
+                && context
+                    .input_object(&gql_type.entity_type())
+                    .map(|input| {
+                        input
+                            .fields
+                            .iter()
+                            .any(|x| x.node.name.node.as_str().to_case(Case::Snake) == name)
+                    })
+                    .unwrap_or(false)
+        })
+        .map(|x| x.node.name.node.as_str().to_case(Case::Snake))
+}
+
+/// Rust expression of the value of a mapped argument.
+fn source_expression<'a>(
+    context: &'a Context,
+    field: &FieldDefinition,
+    source: &ArgumentSource,
+) -> String {
+    match source {
+        ArgumentSource::Argument(name) => {
+            let name = name.to_case(Case::Snake);
+            let is_argument = field
+                .arguments
+                .iter()
+                .any(|x| x.node.name.node.as_str().to_case(Case::Snake) == name);
+
+            match input_field_argument(context, field, &name) {
+                Some(input) if !is_argument => format!("{}.{}.clone()", input, name),
+                _ => format!("{}.clone()", name),
+            }
+        }
+        ArgumentSource::Parent(name) => format!("self.{}.clone()", name.to_case(Case::Snake)),
+        ArgumentSource::Constant(serde_json::Value::String(value)) => {
+            format!("String::from({:?})", value)
+        }
+        ArgumentSource::Constant(serde_json::Value::Bool(value)) => value.to_string(),
+        ArgumentSource::Constant(serde_json::Value::Number(value)) => value.to_string(),
+        ArgumentSource::Constant(value) => format!("serde_json::json!({})", value),
+    }
+}
+
+/// Rust expression of a JSON object built from the mapped nested paths of an argument.
+fn nested_expression<'a>(
+    context: &'a Context,
+    field: &FieldDefinition,
+    nested: &[(&str, &ArgumentSource)],
+) -> String {
+    let mut keys: Vec<&str> = Vec::new();
+    for (path, _) in nested.iter() {
+        let key = path.split('.').next().unwrap_or(path);
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let fields = keys
+        .iter()
+        .map(|key| {
+            let value = match nested.iter().find(|(path, _)| path == key) {
+                Some((_, source)) => source_expression(context, field, source),
+                None => {
+                    let prefix = format!("{}.", key);
+                    let children = nested
+                        .iter()
+                        .filter_map(|(path, source)| {
+                            path.strip_prefix(&prefix).map(|x| (x, *source))
+                        })
+                        .collect::<Vec<_>>();
+                    nested_expression(context, field, &children)
+                }
+            };
+            format!("{:?}: {}", key, value)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!("serde_json::json!({{ {} }})", fields)
+}
+
+/// Bind the method arguments to local variables with the same name, so the call to the method
+/// can be generated:
+/// - Arguments inside the mapping are bound to their source, nested paths to a JSON object.
+/// - Arguments of the field are used as they are.
+/// - Other arguments are bound to the fields of the input object arguments of the field, so a
+///   `petId` field inside an `input: CreatePetInput!` argument is sent as the `petId` argument of
+///   the method.
+fn arguments_bindings<'a>(
+    context: &'a Context,
+    field: &FieldDefinition,
+    arguments: &[String],
+    mapping: &ArgumentsMapping,
+) -> String {
+    let mut bound: Vec<String> = Vec::new();
+
+    arguments
+        .iter()
+        .filter_map(|argument| {
+            let name = argument.to_case(Case::Snake);
+            if bound.contains(&name) {
+                return None;
+            }
+
+            let nested = mapping.nested(argument);
+            let value = match mapping.get(argument) {
+                Some(source) => source_expression(context, field, source),
+                None if !nested.is_empty() => nested_expression(context, field, &nested),
+                None => {
+                    let is_argument = field
+                        .arguments
+                        .iter()
+                        .any(|x| x.node.name.node.as_str().to_case(Case::Snake) == name);
+                    if is_argument {
+                        return None;
+                    }
+
+                    format!("{}.{}", input_field_argument(context, field, &name)?, name)
+                }
+            };
+
+            bound.push(name.clone());
+            Some(format!("let {} = {};", name, value))
+        })
+        .collect::<Vec<String>>()
+        .join("\n    ")
+}
+
+/// Generate a resolver calling a service method with the arguments of the field, and returning
+/// the response as the field type.
+fn generate_service_resolver<'a>(
+    context: &'a Context,
+    service: &str,
+    method_name: &str,
+    args: &ArgumentsMapping,
+    field: &FieldDefinition,
+    scope: &mut Scope,
+    function: &mut Function,
+) -> Result<(), GenericErrors> {
+    let return_type = field.ty.node.to_rust_type(None)?;
+    let method = context
+        .get_service_by_name(service)?
+        .get_a_method(service, method_name)?;
+    let mapping = method.arguments_mapping().merge(args);
+    let bindings = arguments_bindings(context, field, &method.argument_names(), &mapping);
+    let call = service_method_call(context, service, method_name, &return_type, false, scope)?;
+    let headers = headers_binding(context, service, Some(field), scope)?;
+
+    // A nullable field is `null` when the upstream object is not found.
+    let result = if field.ty.node.nullable {
+        format!(
+            r#"match {call}.await {{
+        Err(e) if e.not_found => None,
+        result => result.map_err(|e| e.to_graphql())?,
+    }}"#,
+            call = call
+        )
+    } else {
+        format!("{}.await.map_err(|e| e.to_graphql())?", call)
+    };
+
+    function.line(&format!(
+        r#"
+    {bindings}
+    {headers}
+    let client = ctx.data_unchecked::<{client}>();
+    let result = {result};
+
+    Ok(result)
+                "#,
+        bindings = bindings,
+        headers = headers,
+        client = client_type(service),
+        result = result,
+    ));
+
+    function.ret(format!("FieldResult<{}>", return_type));
+
+    Ok(())
+}
 
 pub struct ServiceBackedQueryDirective {
     pub method_name: String,
     pub service: String,
+    /// Mapping of the method arguments, it takes precedence over the one of the config.
+    pub args: ArgumentsMapping,
 }
 
 impl ServiceBackedQueryDirective {
@@ -16,80 +365,810 @@ impl ServiceBackedQueryDirective {
         field: &FieldDefinition,
         scope: &mut Scope,
         function: &mut Function,
-    ) -> () {
-        let main_name = format!("{}_{}", self.service, self.method_name);
-        let method_name = format!(
-            "{}_{}_method",
-            self.service,
-            self.method_name.to_case(Case::Snake)
-        );
-        let body_args = format!("{}MethodBodyArgs", main_name).to_case(Case::Pascal);
-        let route_args =
-            format!("{}_{}MethodRouteArgs", self.service, self.method_name).to_case(Case::Pascal);
-        let query_args =
-            format!("{}_{}MethodQueryArgs", self.service, self.method_name).to_case(Case::Pascal);
-        scope.import(
-            &format!("crate::infrastructure::{}", self.service),
-            &method_name,
-        );
-        scope.import(
-            &format!("crate::infrastructure::{}", self.service),
-            &body_args,
-        );
-        scope.import(
-            &format!("crate::infrastructure::{}", self.service),
-            &route_args,
-        );
-        scope.import(
-            &format!("crate::infrastructure::{}", self.service),
-            &query_args,
-        );
+    ) -> Result<(), GenericErrors> {
+        generate_service_resolver(
+            context,
+            &self.service,
+            &self.method_name,
+            &self.args,
+            field,
+            scope,
+            function,
+        )
+    }
+}
 
+/// `@serviceBackedMutation` directive, applied on a Mutation field.
+/// The arguments of the mutation, and the fields of its `input`, are sent to the service method,
+/// and the response is mapped into the `*Payload` type.
+pub struct ServiceBackedMutationDirective {
+    pub method_name: String,
+    pub service: String,
+    /// Mapping of the method arguments, it takes precedence over the one of the config.
+    pub args: ArgumentsMapping,
+}
+
+impl ServiceBackedMutationDirective {
+    pub fn generate_method_definition<'a>(
+        &self,
+        context: &'a Context,
+        field: &FieldDefinition,
+        scope: &mut Scope,
+        function: &mut Function,
+    ) -> Result<(), GenericErrors> {
+        generate_service_resolver(
+            context,
+            &self.service,
+            &self.method_name,
+            &self.args,
+            field,
+            scope,
+            function,
+        )
+    }
+}
+
+/// `@serviceBackedSubscription` directive, applied on a Subscription field.
+/// The method must be configured with a `stream` source, every item of the stream is sent to the
+/// subscribers.
+pub struct ServiceBackedSubscriptionDirective {
+    pub method_name: String,
+    pub service: String,
+    /// Mapping of the method arguments, it takes precedence over the one of the config.
+    pub args: ArgumentsMapping,
+}
+
+impl ServiceBackedSubscriptionDirective {
+    pub fn generate_method_definition<'a>(
+        &self,
+        context: &'a Context,
+        field: &FieldDefinition,
+        return_type: &str,
+        scope: &mut Scope,
+        function: &mut Function,
+    ) -> Result<(), GenericErrors> {
         let method = context
-            .get_service_by_name(&self.service)
-            .unwrap()
-            .methods()
-            .get(&self.method_name)
-            .unwrap();
-
-        let route_method_construct =
-            method.route_method_construct(&main_name.to_case(Case::Pascal));
-        let body_method_construct = method.body_method_construct(&main_name.to_case(Case::Pascal));
-        let query_method_construct =
-            method.query_method_construct(&main_name.to_case(Case::Pascal));
+            .get_service_by_name(&self.service)?
+            .get_a_method(&self.service, &self.method_name)?;
+
+        if !method.is_streamable() {
+            return Err(GenericErrors::MethodNotStreamableError(
+                self.service.clone(),
+                self.method_name.clone(),
+            ));
+        }
+
+        let mapping = method.arguments_mapping().merge(&self.args);
+        let bindings = arguments_bindings(context, field, &method.argument_names(), &mapping);
+        let call = service_method_call(
+            context,
+            &self.service,
+            &self.method_name,
+            return_type,
+            true,
+            scope,
+        )?;
+
+        let headers = headers_binding(context, &self.service, Some(field), scope)?;
+
+        scope.import("futures", "Stream");
+        scope.import("futures", "StreamExt");
 
         function.line(&format!(
             r#"
-    let client = reqwest::Client::new();
-    let result = {method}::<{method_type}>(
-        &client,
-        {body},
-        {query},
-        {route}
+    {bindings}
+    {headers}
+    let client = ctx.data_unchecked::<{client}>().0.clone();
+    {call}
+        .map(|result| result.map_err(|e| e.to_graphql()))
+                "#,
+            bindings = bindings,
+            headers = headers,
+            client = client_type(&self.service),
+            call = call,
+        ));
+
+        function.ret(format!("impl Stream<Item = FieldResult<{}>>", return_type));
+
+        Ok(())
+    }
+}
+
+/// `@serviceBackedNode` directive, applied on an Object type.
+/// Fields returning this type only store the id of the node inside the domain, and the node is
+/// resolved through a generated DataLoader calling the service method.
+pub struct ServiceBackedNodeDirective {
+    /// The Object type backed by the service
+    pub node: String,
+    pub method_name: String,
+    pub service: String,
+}
+
+impl ServiceBackedNodeDirective {
+    /// Name of the generated loader
+    pub fn loader_name(&self) -> String {
+        format!("{}Loader", self.node)
+    }
+
+    /// Path of the generated loader
+    pub fn loader_path(&self) -> String {
+        format!(
+            "crate::infrastructure::loader::{}",
+            self.node.to_lowercase()
         )
+    }
+
+    /// Generate the resolver of a field returning this node: the field id stored inside the
+    /// domain is loaded through the DataLoader, with the headers forwarded to a HTTP service.
+    /// The resolver must have a `ctx` argument.
+    pub fn generate_field_definition<'a>(
+        &self,
+        context: &'a Context,
+        field: &FieldDefinition,
+        return_type: &str,
+        scope: &mut Scope,
+        function: &mut Function,
+    ) -> Result<(), AsbruTypeErrors> {
+        scope.import("async_graphql::dataloader", "DataLoader");
+        scope.import(&self.loader_path(), &self.loader_name());
+
+        let headers = headers_binding(context, &self.service, None, scope).map_err(Box::new)?;
+        let key = |id: &str| {
+            if headers.is_empty() {
+                id.to_string()
+            } else {
+                format!("({}, headers.clone())", id)
+            }
+        };
+
+        let field_name = format!("{}_id", field.name.node.as_str().to_case(Case::Snake));
+        let gql_type = &field.ty.node;
+
+        let resolution = match &gql_type.base {
+            BaseType::Named(_) if gql_type.nullable => format!(
+                r#"
+    let value = match &self.{field} {{
+        Some(id) => loader.load_one({key}).await.map_err(|e| e.to_graphql())?,
+        None => None,
+    }};"#,
+                field = field_name,
+                key = key("id.clone()")
+            ),
+            BaseType::Named(_) => format!(
+                r#"
+    let value = loader
+        .load_one({key})
         .await
-        .map_err(|e| {{
-        println!("error: {{:?}}", e);
-        "An error roccured while querying the data from the backend service."
-        }})?;
+        .map_err(|e| e.to_graphql())?
+        .ok_or_else(|| format!("{node} {{}} not found", self.{field}))?;"#,
+                field = field_name,
+                key = key(&format!("self.{}.clone()", field_name)),
+                node = self.node
+            ),
+            BaseType::List(item) => {
+                if let BaseType::List(_) = item.base {
+                    return Err(AsbruTypeErrors::NestedListNodeError(
+                        field.name.node.to_string(),
+                    ));
+                }
+
+                let ids = if gql_type.nullable {
+                    format!("self.{}.iter().flatten()", field_name)
+                } else {
+                    format!("self.{}.iter()", field_name)
+                };
+                let keys = if headers.is_empty() {
+                    ".cloned()".to_string()
+                } else {
+                    ".map(|id| (id.clone(), headers.clone()))".to_string()
+                };
+                let value = if headers.is_empty() {
+                    "values.get(id)".to_string()
+                } else {
+                    format!("values.get(&{})", key("id.clone()"))
+                };
+                let (keys, values) = if item.nullable {
+                    (
+                        format!("{}.flatten(){}", ids, keys),
+                        format!(".map(|id| id.as_ref().and_then(|id| {}.cloned()))", value),
+                    )
+                } else {
+                    (
+                        format!("{}{}", ids, keys),
+                        format!(".filter_map(|id| {}.cloned())", value),
+                    )
+                };
+                let mapping = if gql_type.nullable {
+                    format!(
+                        "self.{field}.as_ref().map(|ids| ids.iter(){values}.collect::<Vec<_>>())",
+                        field = field_name,
+                        values = values
+                    )
+                } else {
+                    format!(
+                        "self.{field}.iter(){values}.collect::<Vec<_>>()",
+                        field = field_name,
+                        values = values
+                    )
+                };
+
+                format!(
+                    r#"
+    let values = loader
+        .load_many({keys})
+        .await
+        .map_err(|e| e.to_graphql())?;
+    let value = {mapping};"#,
+                    keys = keys,
+                    mapping = mapping
+                )
+            }
+        };
+
+        function
+            .line(format!(
+                r#"
+    let loader = ctx.data_unchecked::<DataLoader<{loader}>>();
+    {headers}
+    {resolution}
+
+    Ok(value)
+            "#,
+                loader = self.loader_name(),
+                headers = headers,
+                resolution = resolution
+            ))
+            .ret(format!("FieldResult<{}>", return_type));
+
+        Ok(())
+    }
+
+    /// Generate the DataLoader for this node inside `infrastructure/loader/` and register it
+    /// inside the Schema.
+    /// The service method must have only one argument, which will be filled with the ids of a
+    /// whole batch: a list, or the ids joined with commas inside a query string. The method
+    /// returns the list of nodes, which are matched with the requested ids by their `id` field.
+    /// Calls to a HTTP service are made once for each set of forwarded headers inside the batch.
+    pub fn generate_loader_file<'a>(&self, context: &'a Context) -> Result<(), GenericErrors> {
+        let mut scope = Scope::new();
+        scope.import("std::collections", "HashMap");
+        scope.import("std::sync", "Arc");
+        scope.import("async_graphql::dataloader", "Loader");
+        scope.import("crate::infrastructure::errors", "ServiceError");
+        context.import_path(&self.node, &mut scope);
+
+        let batch = context
+            .get_service_by_name(&self.service)?
+            .get_a_method(&self.service, &self.method_name)?
+            .batch_argument()
+            .map_err(|reason| {
+                GenericErrors::InvalidNodeMethodError(format!(
+                    "{}.{}, {}",
+                    self.service, self.method_name, reason
+                ))
+            })?;
+        let ids = match &batch {
+            BatchArgument::List(argument) => {
+                format!("let {} = ids.to_vec();", argument.to_case(Case::Snake))
+            }
+            BatchArgument::Joined(argument) => {
+                format!("let {} = ids.join(\",\");", argument.to_case(Case::Snake))
+            }
+        };
+
+        let call = service_method_call(
+            context,
+            &self.service,
+            &self.method_name,
+            &format!("Vec<{}>", self.node),
+            false,
+            &mut scope,
+        )?;
+
+        // Nodes without an id can't be matched with the requested ones.
+        let nullable_id = context
+            .type_definition_by_name(&self.node)
+            .and_then(|x| match &x.kind {
+                TypeKind::Object(object) => object
+                    .fields
+                    .iter()
+                    .find(|x| x.node.name.node.as_str() == "id"),
+                _ => None,
+            })
+            .map(|x| x.node.ty.node.nullable)
+            .unwrap_or(false);
+        let by_id = if nullable_id {
+            ".filter_map(|node| Some((node.id.as_ref()?.to_string(), node)))"
+        } else {
+            ".map(|node| (node.id.to_string(), node))"
+        };
 
-        Ok(result)
+        // Nodes which are not found are missing from the result.
+        let nodes = format!(
+            r#"
+            {ids}
+            let nodes = match {call}.await {{
+                Err(e) if e.not_found => Vec::new(),
+                result => result?,
+            }};"#,
+            ids = ids,
+            call = call
+        );
+
+        // Calls to a HTTP service are keyed with the headers sent with them, the batch is split
+        // by headers.
+        let (key_type, load) = match context.get_service_by_name(&self.service)?.header_policy() {
+            Some(_) => {
+                scope.import("crate::infrastructure::headers", "ForwardedHeaders");
+                (
+                    "(String, ForwardedHeaders)",
+                    format!(
+                        r#"
+        let mut batches = HashMap::<&ForwardedHeaders, Vec<String>>::new();
+        for (id, headers) in keys {{
+            batches.entry(headers).or_default().push(id.clone());
+        }}
+
+        let client = &self.client;
+        let values = futures::future::try_join_all(batches.into_iter().map(|(headers, ids)| async move {{
+            {nodes}
+            Ok::<_, ServiceError>(
+                nodes
+                    .into_iter()
+                    {by_id}
+                    .map(|(id, node)| ((id, headers.clone()), node))
+                    .collect::<Vec<_>>(),
+            )
+        }}))
+        .await
+        .map_err(Arc::new)?;
+
+        Ok(values.into_iter().flatten().collect())
                 "#,
-            method = method_name,
-            method_type = &field.ty.node.to_rust_type(None).unwrap(),
-            route = route_method_construct,
-            body = body_method_construct,
-            query = query_method_construct
+                        nodes = nodes,
+                        by_id = by_id
+                    ),
+                )
+            }
+            None => (
+                "String",
+                format!(
+                    r#"
+        let client = &self.client;
+        let ids = keys;
+        {nodes}
+
+        Ok(nodes
+            .into_iter()
+            {by_id}
+            .collect())
+                "#,
+                    nodes = nodes,
+                    by_id = by_id
+                ),
+            ),
+        };
+
+        let mut loader_struct = Struct::new(&self.loader_name());
+        loader_struct.vis("pub").doc(&format!(
+            "DataLoader for `{}`, backed by the `{}` method of the `{}` service.",
+            self.node, self.method_name, self.service
         ));
+        loader_struct.field("client", client_type(&self.service));
+
+        let mut constructor = Impl::new(&self.loader_name());
+        constructor
+            .new_fn("new")
+            .vis("pub")
+            .doc("The loader shares the client of the service stored inside the schema data.")
+            .arg("client", client_type(&self.service))
+            .ret("Self")
+            .line("Self { client }");
+
+        let mut loader_impl = Impl::new(&self.loader_name());
+        loader_impl
+            .impl_trait(format!("Loader<{}>", key_type))
+            .r#macro("#[async_trait::async_trait]")
+            .associate_type("Value", &self.node)
+            .associate_type("Error", "Arc<ServiceError>");
+
+        loader_impl
+            .new_fn("load")
+            .set_async(true)
+            .arg_ref_self()
+            .arg("keys", format!("&[{}]", key_type))
+            .ret(format!(
+                "Result<HashMap<{}, Self::Value>, Self::Error>",
+                key_type
+            ))
+            .line(load);
+
+        scope
+            .push_struct(loader_struct)
+            .push_impl(constructor)
+            .push_impl(loader_impl);
+
+        context.create_a_new_file(
+            format!("infrastructure/loader/{}.rs", self.node.to_lowercase()),
+            scope.to_string().as_bytes(),
+        )?;
+
+        let main_file = context.main_file();
+        main_file
+            .main_scope()
+            .import("async_graphql::dataloader", "DataLoader");
+        main_file
+            .main_scope()
+            .import(&self.loader_path(), &self.loader_name());
+        main_file.schema_data(format!(
+            "DataLoader::new({}::new({}_client.clone()))",
+            self.loader_name(),
+            self.service
+        ));
+
+        Ok(())
+    }
+}
+
+/// `@serviceBackedConnection` directive, applied on a Connection type.
+/// Fields returning this Connection are resolved by calling the service method with the
+/// pagination arguments, the upstream page is then mapped into the Relay Connection.
+pub struct ServiceBackedConnectionDirective {
+    /// The Connection type backed by the service
+    pub connection: String,
+    pub method_name: String,
+    pub service: String,
+    /// The method argument which will be filled with the id of the parent object.
+    pub source_id_request_field_name: Option<String>,
+}
+
+impl ServiceBackedConnectionDirective {
+    /// Name of the generated page structure
+    fn page_name(&self) -> String {
+        format!("{}Page", self.connection)
+    }
+
+    /// Generate the structures describing a page from the service, it mirrors the Connection
+    /// type: `edges`, `pageInfo` and additional fields.
+    fn generate_page_structs(
+        &self,
+        node_type: &str,
+        connection_fields: Option<&str>,
+        edge_fields: Option<&str>,
+        scope: &mut Scope,
+    ) {
+        scope.import("serde", "Deserialize");
+
+        let page_info_name = format!("{}PageInfo", self.connection);
+        let mut page_info = Struct::new(&page_info_name);
+        page_info
+            .vis("pub")
+            .derive("Deserialize")
+            .derive("Debug")
+            .derive("Default");
+        page_info.field(
+            "#[serde(alias = \"hasPreviousPage\")]\npub has_previous_page",
+            "bool",
+        );
+        page_info.field(
+            "#[serde(alias = \"hasNextPage\")]\npub has_next_page",
+            "bool",
+        );
+
+        let edge_name = format!("{}PageEdge", self.connection);
+        let mut edge = Struct::new(&edge_name);
+        edge.vis("pub").derive("Deserialize").derive("Debug");
+        edge.field("pub cursor", "String");
+        edge.field("pub node", node_type);
+        if let Some(edge_fields) = edge_fields {
+            edge.field("#[serde(flatten)]\npub additional_fields", edge_fields);
+        }
+
+        let mut page = Struct::new(&self.page_name());
+        page.vis("pub").derive("Deserialize").derive("Debug");
+        page.field("pub edges", format!("Vec<{}>", edge_name));
+        page.field(
+            "#[serde(default, alias = \"pageInfo\")]\npub page_info",
+            &page_info_name,
+        );
+        if let Some(connection_fields) = connection_fields {
+            page.field(
+                "#[serde(flatten)]\npub additional_fields",
+                connection_fields,
+            );
+        }
+
+        scope
+            .push_struct(page_info)
+            .push_struct(edge)
+            .push_struct(page);
+    }
 
-        function.ret(format!(
-            "FieldResult<{}>",
-            field.ty.node.to_rust_type(None).unwrap()
+    /// Generate the resolver of a field returning this Connection.
+    /// Pagination arguments of the field are forwarded to the method arguments with the same
+    /// name, and the id of the parent object to the `sourceIdRequestFieldName` argument: the
+    /// validator checks the parent has an `id` field.
+    pub fn generate_field_definition<'a>(
+        &self,
+        context: &'a Context,
+        field: &FieldDefinition,
+        node_type: &str,
+        connection_fields: Option<&str>,
+        edge_fields: Option<&str>,
+        return_type: &str,
+        scope: &mut Scope,
+        function: &mut Function,
+    ) -> Result<(), GenericErrors> {
+        self.generate_page_structs(node_type, connection_fields, edge_fields, scope);
+
+        let method = context
+            .get_service_by_name(&self.service)?
+            .get_a_method(&self.service, &self.method_name)?;
+        let arguments = method.argument_names();
+        let call = service_method_call(
+            context,
+            &self.service,
+            &self.method_name,
+            &self.page_name(),
+            false,
+            scope,
+        )?;
+
+        let pagination_argument = |name: &str| {
+            field
+                .arguments
+                .iter()
+                .find(|x| x.node.name.node.as_str() == name)
+                .map(|x| {
+                    if x.node.ty.node.nullable {
+                        name.to_string()
+                    } else {
+                        format!("Some({})", name)
+                    }
+                })
+                .unwrap_or_else(|| "None".to_string())
+        };
+
+        let source = match &self.source_id_request_field_name {
+            Some(source) => format!("let {} = self.id.to_string();", source.to_case(Case::Snake)),
+            None => "".to_string(),
+        };
+
+        // Pagination arguments of a HTTP method are optional, only those present are sent. A
+        // gRPC message has no presence: missing arguments are sent as their default value.
+        let pagination = arguments
+            .iter()
+            .filter_map(|argument| match (&method, argument.as_str()) {
+                (Method::HTTP(_), "after") | (Method::HTTP(_), "before") => None,
+                (Method::HTTP(_), "first") | (Method::HTTP(_), "last") => Some(format!(
+                    "let {arg} = {arg}.map(|x| x.to_string());",
+                    arg = argument
+                )),
+                (Method::GRPC(_), "after") | (Method::GRPC(_), "before") => Some(format!(
+                    "let {arg} = {arg}.unwrap_or_default();",
+                    arg = argument
+                )),
+                (Method::GRPC(_), "first") | (Method::GRPC(_), "last") => Some(format!(
+                    "let {arg} = {arg}.map(|x| x.to_string()).unwrap_or_default();",
+                    arg = argument
+                )),
+                _ => None,
+            })
+            .collect::<Vec<String>>()
+            .join("\n            ");
+
+        let connection = match connection_fields {
+            Some(_) => "Connection::with_additional_fields(page.page_info.has_previous_page, page.page_info.has_next_page, page.additional_fields)",
+            None => "Connection::new(page.page_info.has_previous_page, page.page_info.has_next_page)",
+        };
+        let edge = match edge_fields {
+            Some(_) => {
+                "Edge::with_additional_fields(edge.cursor, edge.node, edge.additional_fields)"
+            }
+            None => "Edge::new(edge.cursor, edge.node)",
+        };
+
+        function
+            .line(format!(
+                r#"
+    {source}
+    {headers}
+    let client = ctx.data_unchecked::<{client}>();
+    query(
+        {after},
+        {before},
+        {first},
+        {last},
+        |after: Option<String>, before: Option<String>, first: Option<usize>, last: Option<usize>| async move {{
+            {pagination}
+            let page = {call}.await.map_err(|e| e.to_graphql())?;
+
+            let mut connection = {connection};
+            connection.append(page.edges.into_iter().map(|edge| {edge}));
+
+            Ok::<_, async_graphql::Error>(connection)
+        }},
+    )
+    .await
+            "#,
+                source = source,
+                headers = headers_binding(context, &self.service, Some(field), scope)?,
+                after = pagination_argument("after"),
+                before = pagination_argument("before"),
+                first = pagination_argument("first"),
+                last = pagination_argument("last"),
+                pagination = pagination,
+                client = client_type(&self.service),
+                call = call,
+                connection = connection,
+                edge = edge
+            ))
+            .ret(format!("FieldResult<{}>", return_type));
+
+        Ok(())
+    }
+}
+
+/// `@derivedField(provider: "crate::derived::is_host")`: a field computed by a user-supplied
+/// function instead of being read from the domain or loaded from a service.
+pub struct DerivedFieldDirective {
+    /// Path of the provider, an `async fn(&Parent, &Context<'_>, ...arguments) -> FieldResult<T>`.
+    pub provider: String,
+}
+
+impl DerivedFieldDirective {
+    /// Generate the resolver, which awaits the provider called with the parent domain struct, the
+    /// context and the arguments of the field. The `ctx` argument must already be declared.
+    pub fn generate_method_definition(
+        &self,
+        field: &FieldDefinition,
+        return_type: &str,
+        function: &mut Function,
+    ) -> () {
+        let arguments = field
+            .arguments
+            .iter()
+            .map(|x| format!(", {}", x.node.name.node.as_str().to_case(Case::Snake)))
+            .collect::<Vec<String>>()
+            .join("");
+
+        function
+            .line(format!("{}(self, ctx{}).await", self.provider, arguments))
+            .ret(format!("FieldResult<{}>", return_type));
+    }
+}
+
+/// Directives requiring scopes, with the argument listing them.
+pub const SCOPE_DIRECTIVES: [(&str, &str); 2] = [("scope", "scopes"), ("auth", "requires")];
+
+/// `@scope(scopes: ["pets:read"])` or `@auth(requires: ["pets:read"])`, applied on an Object type
+/// or a field: the caller must be authenticated with every listed scope. Without scopes, the
+/// caller must only be authenticated. On a type, it applies to every field of the type.
+pub struct ScopeDirective {
+    pub scopes: Vec<String>,
+}
+
+impl ScopeDirective {
+    /// Scopes required by the directives, `None` without scope directive.
+    pub fn from_directives(directives: &[Positioned<ConstDirective>]) -> Option<Self> {
+        let directives = directives
+            .iter()
+            .filter_map(|directive| {
+                SCOPE_DIRECTIVES
+                    .iter()
+                    .find(|(name, _)| directive.node.name.node.as_str() == *name)
+                    .map(|(_, argument)| directive.node.get_argument(argument))
+            })
+            .collect::<Vec<_>>();
+        if directives.is_empty() {
+            return None;
+        }
+
+        let mut scopes: Vec<String> = Vec::new();
+        for argument in directives.into_iter().flatten() {
+            if let ConstValue::List(values) = &argument.node {
+                for value in values.iter() {
+                    if let ConstValue::String(scope) = value {
+                        if !scopes.contains(scope) {
+                            scopes.push(scope.to_owned());
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(ScopeDirective { scopes })
+    }
+
+    /// Scopes required to resolve a field, by the field and its parent type.
+    pub fn required(parent: Option<&TypeDefinition>, field: &FieldDefinition) -> Option<Self> {
+        let parent = parent.and_then(|x| Self::from_directives(&x.directives));
+        let field = Self::from_directives(&field.directives);
+
+        match (parent, field) {
+            (Some(mut parent), Some(field)) => {
+                for scope in field.scopes {
+                    if !parent.scopes.contains(&scope) {
+                        parent.scopes.push(scope);
+                    }
+                }
+                Some(parent)
+            }
+            (parent, field) => parent.or(field),
+        }
+    }
+
+    /// Guard the resolver with a `ScopeGuard` checking the claims of the caller.
+    pub fn generate_guard(&self, scope: &mut Scope, function: &mut Function) {
+        scope.import("crate::infrastructure::auth", "ScopeGuard");
+
+        let scopes = self
+            .scopes
+            .iter()
+            .map(|x| format!("{:?}", x))
+            .collect::<Vec<String>>()
+            .join(", ");
+        function.attr(&format!(
+            "graphql(guard(ScopeGuard(scopes = {:?})))",
+            format!("&[{}][..]", scopes)
         ));
     }
 }
+
+/// `@key(path: "profile.email")`: where a field is read from inside the upstream object.
 pub struct KeyDirective {
-    pub key: String,
+    pub path: String,
+}
+
+impl KeyDirective {
+    /// Serde attributes of the domain field `name`, with a trailing new line.
+    ///
+    /// A key at the top level of the object is an alias. A nested path can't be, so the field
+    /// is flattened and read from the whole object by a module generated inside the scope.
+    pub fn field_attributes(&self, name: &str, scope: &mut Scope) -> String {
+        if !self.path.contains('.') && !self.path.starts_with('/') {
+            return format!("#[serde(alias = \"{}\")]\n", self.path);
+        }
+
+        let module = format!("{}_at_path", name);
+        scope.raw(&format!(
+            r#"/// `{name}` is read from `{path}`.
+mod {module} {{
+    use serde::ser::SerializeMap;
+    use serde::{{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer}};
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: DeserializeOwned,
+    {{
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let value = value
+            .pointer("{pointer}")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+
+        serde_json::from_value(value).map_err(serde::de::Error::custom)
+    }}
+
+    pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {{
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("{name}", value)?;
+        map.end()
+    }}
+}}"#,
+            name = name,
+            path = self.path,
+            module = module,
+            pointer = json_pointer(&self.path)
+        ));
+
+        format!("#[serde(flatten, with = \"{}\")]\n", module)
+    }
 }
 
 pub struct RemapDirective {
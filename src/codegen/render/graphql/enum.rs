@@ -1,5 +1,6 @@
 use crate::codegen::render::graphql::enum_value_definition::EnumDefinitionExt;
 use crate::codegen::render::graphql::field::FieldDefinitionExt;
+use crate::codegen::render::graphql::scal::asbru_type::AsbruTypeErrors;
 use crate::codegen::{context::Context, generate::GenericErrors, render::render::Render};
 use async_graphql_parser::types::{TypeDefinition, TypeKind};
 use codegen::{Enum, Impl, Scope};
@@ -40,20 +41,23 @@ impl<'a> EnumWrapper<'a> {
             .derive("PartialEq")
             .derive("Copy");
 
-        // Add field for it.
-        let _fields = match &self.doc.kind {
+        // Add field for it, an invalid value is reported at its position.
+        match &self.doc.kind {
             TypeKind::Enum(enum_values) => &enum_values.values,
             _ => {
                 return Err(GenericErrors::GenericGeneratorError);
             }
         }
         .iter()
-        .filter_map(|x| {
-            let enum_value = &x.node;
-            enum_value.generate_enum_value(enum_struct);
-            Some(())
-        })
-        .collect::<Vec<()>>();
+        .try_for_each(|x| {
+            x.node.generate_enum_value(enum_struct).map_err(|e| {
+                AsbruTypeErrors::LocatedError(
+                    format!("{}.{}", self.object_name(), x.node.value.node),
+                    x.pos,
+                    Box::new(e.into()),
+                )
+            })
+        })?;
 
         self.context.create_a_new_file(
             format!("domain/{}", &self.domain_name()),
@@ -3,38 +3,52 @@ use async_graphql_value::ConstValue;
 use codegen::Enum;
 
 use crate::codegen::render::graphql::directive::RenameDirective;
+use crate::codegen::render::graphql::fie::asbru_type::AsbruFieldExtErrors;
 
 pub trait EnumDefinitionExt {
     /// Add a value to an enum
-    fn generate_enum_value(&self, enum_struct: &mut Enum) -> ();
+    fn generate_enum_value(&self, enum_struct: &mut Enum) -> Result<(), AsbruFieldExtErrors>;
     /// Rename directive
-    fn rename_directive(&self) -> Option<RenameDirective>;
+    /// If the directive is invalid, it'll result in an error.
+    fn rename_directive(&self) -> Result<Option<RenameDirective>, AsbruFieldExtErrors>;
 }
 
 impl EnumDefinitionExt for EnumValueDefinition {
-    fn rename_directive(&self) -> Option<RenameDirective> {
-        let directive = self
+    fn rename_directive(&self) -> Result<Option<RenameDirective>, AsbruFieldExtErrors> {
+        let directive = match self
             .directives
             .iter()
             .find(|x| x.node.name.node.as_str() == "rename")
-            .map(|x| &x.node)?;
+        {
+            Some(directive) => &directive.node,
+            None => return Ok(None),
+        };
 
-        let name = match &directive.get_argument("name").unwrap().node {
-            ConstValue::String(value) => value,
-            _ => panic!("A directive is malformed"),
-        }
-        .to_owned();
-        Some(RenameDirective { name })
+        let name = match directive.get_argument("name").map(|x| &x.node) {
+            Some(ConstValue::String(value)) => value.to_owned(),
+            None => {
+                return Err(AsbruFieldExtErrors::ArgumentMissingError(
+                    "name of @rename".to_string(),
+                ))
+            }
+            _ => {
+                return Err(AsbruFieldExtErrors::ArgumentTypeError(
+                    "name of @rename".to_string(),
+                ))
+            }
+        };
+        Ok(Some(RenameDirective { name }))
     }
 
-    fn generate_enum_value(&self, enum_struct: &mut Enum) -> () {
+    fn generate_enum_value(&self, enum_struct: &mut Enum) -> Result<(), AsbruFieldExtErrors> {
         let name = self.value.node.as_str().to_uppercase();
 
         let opt_alias = self
-            .rename_directive()
+            .rename_directive()?
             .map(|x| format!("#[serde(rename = \"{}\")]\n", x.name))
             .unwrap_or("".to_string());
 
         enum_struct.new_variant(&format!("{}{}", opt_alias, name));
+        Ok(())
     }
 }
@@ -35,17 +35,23 @@ impl AsbruFieldExt for FieldDefinition {
             .directives
             .iter()
             .filter_map(|x| match x.node.name.node.as_str() {
-                "key" => match x.node.get_argument("key").map(|x| &x.node) {
+                // `key` is the former name of the `path` argument.
+                "key" => match x
+                    .node
+                    .get_argument("path")
+                    .or_else(|| x.node.get_argument("key"))
+                    .map(|x| &x.node)
+                {
                     Some(ConstValue::String(value)) => {
                         Some(Ok(FieldDirectives::KeyDirective(KeyDirective {
-                            key: value.to_owned(),
+                            path: value.to_owned(),
                         })))
                     }
                     None => Some(Err(AsbruFieldExtErrors::ArgumentMissingError(
-                        "key".to_string(),
+                        "path".to_string(),
                     ))),
                     _ => Some(Err(AsbruFieldExtErrors::ArgumentTypeError(
-                        "key".to_string(),
+                        "path".to_string(),
                     ))),
                 },
                 "fromNumber" => Some(Ok(FieldDirectives::RemapDirective(RemapDirective {
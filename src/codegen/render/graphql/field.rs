@@ -1,11 +1,43 @@
+use crate::codegen::config::ArgumentsMapping;
 use crate::codegen::{context::Context, generate::GenericErrors, render::render::Render};
-use async_graphql_parser::types::FieldDefinition;
+use async_graphql_parser::types::{ConstDirective, FieldDefinition};
 use async_graphql_value::ConstValue;
 
-use super::{directive::ServiceBackedQueryDirective, gql_types::GraphQLType, scalars::ToRustType};
+use super::{
+    directive::{
+        DerivedFieldDirective, ServiceBackedMutationDirective, ServiceBackedQueryDirective,
+        ServiceBackedSubscriptionDirective,
+    },
+    fie::asbru_type::AsbruFieldExtErrors,
+    gql_types::GraphQLType,
+    scalars::ToRustType,
+};
 
 pub trait FieldDefinitionExt {
-    fn service_backed_query(&self) -> Option<ServiceBackedQueryDirective>;
+    /// Get the `@serviceBackedQuery` directive of this field.
+    /// If the directive is invalid, it'll result in an error.
+    fn service_backed_query(
+        &self,
+    ) -> Result<Option<ServiceBackedQueryDirective>, AsbruFieldExtErrors>;
+    /// Get the `@serviceBackedMutation` directive of this field.
+    /// If the directive is invalid, it'll result in an error.
+    fn service_backed_mutation(
+        &self,
+    ) -> Result<Option<ServiceBackedMutationDirective>, AsbruFieldExtErrors>;
+    /// Get the `@serviceBackedSubscription` directive of this field.
+    /// If the directive is invalid, it'll result in an error.
+    fn service_backed_subscription(
+        &self,
+    ) -> Result<Option<ServiceBackedSubscriptionDirective>, AsbruFieldExtErrors>;
+    /// The service, the method and the arguments mapping backing this field, whatever the
+    /// directive used.
+    /// If the directive is invalid, it'll result in an error.
+    fn service_backed_method(
+        &self,
+    ) -> Result<Option<(String, String, ArgumentsMapping)>, AsbruFieldExtErrors>;
+    /// Get the `@derivedField` directive of this field.
+    /// If the directive is invalid, it'll result in an error.
+    fn derived_field(&self) -> Result<Option<DerivedFieldDirective>, AsbruFieldExtErrors>;
     fn from_number(&self) -> bool;
     // fn key_directive(&self) -> Option<KeyDirective>;
     fn is_native_gql_type<'a>(&self, context: &'a Context) -> Result<GraphQLType, GenericErrors>;
@@ -33,28 +65,123 @@ pub trait FieldDefinitionExt {
     fn interface_field_macro(&self) -> String;
 }
 
+/// Name of an argument of a directive, to report it.
+fn argument_name(directive: &ConstDirective, name: &str) -> String {
+    format!("{} of @{}", name, directive.name.node)
+}
+
+/// Get a String argument from a directive.
+fn string_argument(directive: &ConstDirective, name: &str) -> Result<String, AsbruFieldExtErrors> {
+    match directive.get_argument(name).map(|x| &x.node) {
+        Some(ConstValue::String(value)) => Ok(value.to_owned()),
+        None => Err(AsbruFieldExtErrors::ArgumentMissingError(argument_name(
+            directive, name,
+        ))),
+        _ => Err(AsbruFieldExtErrors::ArgumentTypeError(argument_name(
+            directive, name,
+        ))),
+    }
+}
+
+/// Parse the `args` argument of a data directive, the mapping of the method arguments.
+fn arguments_mapping(directive: &ConstDirective) -> Result<ArgumentsMapping, AsbruFieldExtErrors> {
+    match directive.get_argument("args") {
+        Some(args) => match args.node.clone().into_json() {
+            Ok(args @ serde_json::Value::Object(_)) => Ok(ArgumentsMapping::from_json(&args)),
+            _ => Err(AsbruFieldExtErrors::ArgumentTypeError(argument_name(
+                directive, "args",
+            ))),
+        },
+        None => Ok(ArgumentsMapping::default()),
+    }
+}
+
 impl FieldDefinitionExt for FieldDefinition {
-    fn service_backed_query(&self) -> Option<ServiceBackedQueryDirective> {
-        let directive = self
+    fn service_backed_query(
+        &self,
+    ) -> Result<Option<ServiceBackedQueryDirective>, AsbruFieldExtErrors> {
+        let directive = match self
             .directives
             .iter()
             .find(|x| x.node.name.node.as_str() == "serviceBackedQuery")
-            .map(|x| &x.node)?;
+        {
+            Some(directive) => &directive.node,
+            None => return Ok(None),
+        };
 
-        let method_name = match &directive.get_argument("methodName").unwrap().node {
-            ConstValue::String(value) => value,
-            _ => panic!("A directive is malformed"),
+        Ok(Some(ServiceBackedQueryDirective {
+            method_name: string_argument(directive, "methodName")?,
+            service: string_argument(directive, "service")?,
+            args: arguments_mapping(directive)?,
+        }))
+    }
+
+    fn service_backed_mutation(
+        &self,
+    ) -> Result<Option<ServiceBackedMutationDirective>, AsbruFieldExtErrors> {
+        let directive = match self
+            .directives
+            .iter()
+            .find(|x| x.node.name.node.as_str() == "serviceBackedMutation")
+        {
+            Some(directive) => &directive.node,
+            None => return Ok(None),
+        };
+
+        Ok(Some(ServiceBackedMutationDirective {
+            method_name: string_argument(directive, "methodName")?,
+            service: string_argument(directive, "service")?,
+            args: arguments_mapping(directive)?,
+        }))
+    }
+
+    fn service_backed_subscription(
+        &self,
+    ) -> Result<Option<ServiceBackedSubscriptionDirective>, AsbruFieldExtErrors> {
+        let directive = match self
+            .directives
+            .iter()
+            .find(|x| x.node.name.node.as_str() == "serviceBackedSubscription")
+        {
+            Some(directive) => &directive.node,
+            None => return Ok(None),
+        };
+
+        Ok(Some(ServiceBackedSubscriptionDirective {
+            method_name: string_argument(directive, "methodName")?,
+            service: string_argument(directive, "service")?,
+            args: arguments_mapping(directive)?,
+        }))
+    }
+
+    fn service_backed_method(
+        &self,
+    ) -> Result<Option<(String, String, ArgumentsMapping)>, AsbruFieldExtErrors> {
+        if let Some(x) = self.service_backed_query()? {
+            return Ok(Some((x.service, x.method_name, x.args)));
         }
-        .to_owned();
-        let service = match &directive.get_argument("service").unwrap().node {
-            ConstValue::String(value) => value,
-            _ => panic!("A directive is malformed"),
+        if let Some(x) = self.service_backed_mutation()? {
+            return Ok(Some((x.service, x.method_name, x.args)));
         }
-        .to_owned();
-        Some(ServiceBackedQueryDirective {
-            method_name,
-            service,
-        })
+
+        Ok(self
+            .service_backed_subscription()?
+            .map(|x| (x.service, x.method_name, x.args)))
+    }
+
+    fn derived_field(&self) -> Result<Option<DerivedFieldDirective>, AsbruFieldExtErrors> {
+        let directive = match self
+            .directives
+            .iter()
+            .find(|x| x.node.name.node.as_str() == "derivedField")
+        {
+            Some(directive) => &directive.node,
+            None => return Ok(None),
+        };
+
+        Ok(Some(DerivedFieldDirective {
+            provider: string_argument(directive, "provider")?,
+        }))
     }
 
     /*
@@ -22,35 +22,40 @@ impl<'a> InputWrapper<'a> {
         format!("{}.rs", self.object_name().to_lowercase())
     }
 
-    /// Generate an enum file for the actual type.
-    /// We create a representation for each fields with no arguments and no directive.
+    /// Generate an input file for the actual type.
+    /// We create a representation for each fields, nested inputs and enums are imported.
     pub fn generate_input(&self) -> Result<(), GenericErrors> {
         let mut scope = Scope::new();
         scope.import("serde", "Serialize");
         scope.import("serde", "Deserialize");
+        scope.import("async_graphql", "*");
 
         let mut object_struct = Struct::new(self.object_name());
 
         object_struct
             .vis("pub")
             .derive("InputObject")
+            .derive("Serialize")
+            .derive("Deserialize")
+            .derive("Debug")
             .derive("Clone");
 
+        if let Some(description) = &self.doc.description {
+            object_struct.doc(&description.node);
+        }
+
         // Add field for it.
-        let _fields = match &self.doc.kind {
+        match &self.doc.kind {
             TypeKind::InputObject(input) => &input.fields,
             _ => {
                 return Err(GenericErrors::GenericGeneratorError);
             }
         }
         .iter()
-        .filter_map(|x| {
+        .try_for_each(|x| {
             x.node
-                .generate_input_struct(&self.context, &mut scope, &mut object_struct);
-            // If it's another entity, we should have only their getting method.
-            Some((&x.node.name.node, &x.node.description))
-        })
-        .collect::<Vec<_>>();
+                .generate_input_struct(&self.context, &mut scope, &mut object_struct)
+        })?;
 
         scope.push_struct(object_struct);
 
@@ -1,20 +1,73 @@
-use async_graphql_parser::types::InputValueDefinition;
-use codegen::{Scope, Struct};
+use async_graphql_parser::types::{BaseType, InputValueDefinition, Type};
+use async_graphql_value::ConstValue;
+use codegen::{Field, Scope, Struct};
 use convert_case::{Case, Casing};
 
-use crate::codegen::context::Context;
+use crate::codegen::{context::Context, generate::GenericErrors};
 
-use super::{gql_types::GraphQLType, scalars::ToRustType};
+use super::scalars::ToRustType;
 
 pub trait InputDefinitionExt {
     fn from_number(&self) -> bool;
-    /// Add a value to an enum
+    /// Add a field to an input struct
     fn generate_input_struct<'a>(
         &self,
         context: &'a Context,
         scope: &mut Scope,
         domain_struct: &mut Struct,
-    ) -> ();
+    ) -> Result<(), GenericErrors>;
+}
+
+/// Generate a Rust expression from a GraphQL value for the given type.
+/// Used to describe default values, returns None when the value can't be described.
+fn const_value_expression(
+    context: &Context,
+    value: &ConstValue,
+    gql_type: &Type,
+) -> Option<String> {
+    let expression = match (value, &gql_type.base) {
+        (ConstValue::Null, _) => {
+            return if gql_type.nullable {
+                Some("None".to_string())
+            } else {
+                None
+            }
+        }
+        (ConstValue::List(values), BaseType::List(item_type)) => format!(
+            "vec![{}]",
+            values
+                .iter()
+                .map(|x| const_value_expression(context, x, item_type))
+                .collect::<Option<Vec<String>>>()?
+                .join(", ")
+        ),
+        // Input coercion: a single value is accepted for a list.
+        (value, BaseType::List(item_type)) => {
+            format!(
+                "vec![{}]",
+                const_value_expression(context, value, item_type)?
+            )
+        }
+        (ConstValue::Number(number), BaseType::Named(name)) => match name.as_str() {
+            "Float" => format!("{}_f64", number),
+            _ => number.to_string(),
+        },
+        (ConstValue::String(value), BaseType::Named(name)) => match name.as_str() {
+            "ID" => format!("ID({:?}.to_string())", value),
+            _ => format!("{:?}.to_string()", value),
+        },
+        (ConstValue::Boolean(value), BaseType::Named(_)) => value.to_string(),
+        (ConstValue::Enum(value), BaseType::Named(name)) if context.is_enum(name.as_str()) => {
+            format!("{}::{}", name, value.as_str().to_uppercase())
+        }
+        _ => return None,
+    };
+
+    if gql_type.nullable {
+        Some(format!("Some({})", expression))
+    } else {
+        Some(expression)
+    }
 }
 
 impl InputDefinitionExt for InputValueDefinition {
@@ -33,51 +86,32 @@ impl InputDefinitionExt for InputValueDefinition {
         context: &'a Context,
         scope: &mut Scope,
         domain_struct: &mut Struct,
-    ) -> () {
+    ) -> Result<(), GenericErrors> {
         let gql_type = &self.ty.node;
         let gql_name = &self.name.node;
 
-        if self.from_number() {
-            domain_struct.field(&format!("pub {}", gql_name), "u32");
-            return;
-        };
+        // Enums, custom scalars and nested inputs are imported, native types are not.
+        context.import_path(gql_type.entity_type(), scope);
 
-        // We should check if the type is either:
-        // - Native
-        // - Non-Native:
-        //  - Enum
-        //  - Interface? (WIP)
-        match gql_type.is_native_gql_type(context).unwrap() {
-            GraphQLType::NativeType => {
-                let type_name = &*gql_type.to_rust_type(None).unwrap();
-                domain_struct.field(
-                    &format!("pub {}", gql_name.to_case(Case::Snake)),
-                    match type_name {
-                        "ID" => "String",
-                        _ => type_name,
-                    },
-                );
-            }
-            GraphQLType::EnumType => {
-                scope.import(
-                    &format!("crate::domain::{}", &gql_type.entity_type().to_lowercase()),
-                    &gql_type.entity_type(),
-                );
+        let type_name = gql_type.to_rust_type(Some("i32").filter(|_| self.from_number()))?;
 
-                let type_name = &*gql_type.to_rust_type(None).unwrap();
-                domain_struct.field(&format!("pub {}", gql_name.to_case(Case::Snake)), type_name);
-            }
-            _ => {
-                let type_name = &*gql_type.to_rust_type(Some("String")).unwrap();
+        let opt_default = self
+            .default_value
+            .as_ref()
+            .and_then(|x| const_value_expression(context, &x.node, gql_type))
+            .map(|x| format!("#[graphql(default_with = {:?})]\n", x))
+            .unwrap_or("".to_string());
 
-                domain_struct.field(
-                    &format!("pub {}_id", gql_name),
-                    match type_name {
-                        "ID" => "String",
-                        _ => type_name,
-                    },
-                );
-            }
+        let mut field = Field::new(
+            &format!("{}pub {}", opt_default, gql_name.to_case(Case::Snake)),
+            type_name,
+        );
+
+        if let Some(description) = &self.description {
+            field.doc(description.node.lines().collect::<Vec<&str>>());
         }
+
+        domain_struct.push_field(field);
+        Ok(())
     }
 }
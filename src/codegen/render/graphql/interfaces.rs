@@ -1,9 +1,68 @@
 use crate::codegen::context::auto_import::AutoImport;
 use crate::codegen::render::graphql::field::FieldDefinitionExt;
+use crate::codegen::render::graphql::scalars::ToRustType;
 use crate::codegen::{context::Context, generate::GenericErrors, render::render::Render};
-use async_graphql_parser::types::{TypeDefinition, TypeKind};
+use async_graphql_parser::types::{FieldDefinition, TypeDefinition, TypeKind};
 use codegen::{Enum, Scope, Variant};
 
+/// Interfaces implemented by a type, directly or through the interfaces it implements, closest
+/// first. `lookup` finds a type definition by its name, unknown names are skipped.
+pub fn interface_ancestors<'a, F>(type_def: &'a TypeDefinition, lookup: F) -> Vec<&'a str>
+where
+    F: Fn(&str) -> Option<&'a TypeDefinition>,
+{
+    let implements = |type_def: &'a TypeDefinition| -> Vec<&'a str> {
+        match &type_def.kind {
+            TypeKind::Object(object) => object.implements.iter().map(|x| x.node.as_str()).collect(),
+            TypeKind::Interface(interface) => interface
+                .implements
+                .iter()
+                .map(|x| x.node.as_str())
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+
+    let mut ancestors: Vec<&'a str> = Vec::new();
+    let mut queue = implements(type_def);
+    while !queue.is_empty() {
+        let name = queue.remove(0);
+        if ancestors.contains(&name) {
+            continue;
+        }
+        ancestors.push(name);
+        if let Some(interface) = lookup(name) {
+            queue.extend(implements(interface));
+        }
+    }
+
+    ancestors
+}
+
+/// Fields of an interface, with the ones inherited from its ancestors it doesn't redeclare.
+pub fn interface_fields<'a, F>(type_def: &'a TypeDefinition, lookup: F) -> Vec<&'a FieldDefinition>
+where
+    F: Fn(&str) -> Option<&'a TypeDefinition> + Copy,
+{
+    let own = std::iter::once(type_def);
+    let ancestors = interface_ancestors(type_def, lookup)
+        .into_iter()
+        .filter_map(lookup);
+
+    let mut fields: Vec<&'a FieldDefinition> = Vec::new();
+    for interface in own.chain(ancestors) {
+        if let TypeKind::Interface(interface) = &interface.kind {
+            for field in interface.fields.iter() {
+                if !fields.iter().any(|x| x.name.node == field.node.name.node) {
+                    fields.push(&field.node);
+                }
+            }
+        }
+    }
+
+    fields
+}
+
 pub struct InterfaceWrapper<'a> {
     // We store the whole type definition because we might need directives but it's an enum, we
     // should refine this type later.
@@ -21,7 +80,9 @@ impl<'a> InterfaceWrapper<'a> {
     }
 
     /// Generate an interface file for the actual type.
-    /// We create a representation for each fields with no arguments and no directive.
+    /// We create a representation for each fields with no arguments and no directive, the fields
+    /// inherited from the interfaces it implements included.
+    /// Every object implementing it, directly or through an other interface, is a variant.
     pub fn generate_interface(&self) -> Result<(), GenericErrors> {
         let mut scope = Scope::new();
         scope.import("async_graphql", "*");
@@ -29,21 +90,19 @@ impl<'a> InterfaceWrapper<'a> {
         let mut enum_struct = Enum::new(&format!("{}", self.object_name()));
         enum_struct.vis("pub").derive("Interface");
 
-        // TODO: implements
-        let interface = match &self.doc.kind {
-            TypeKind::Interface(interface) => interface,
-            _ => {
-                return Err(GenericErrors::GenericGeneratorError);
-            }
-        };
+        if !matches!(self.doc.kind, TypeKind::Interface(_)) {
+            return Err(GenericErrors::GenericGeneratorError);
+        }
 
         println!("Interface: {:?}", &self.doc);
 
-        let fields = interface
-            .fields
-            .iter()
-            .map(|x| {
-                let field = &x.node;
+        let lookup = |name: &str| self.context.type_definition_by_name(name);
+        let fields = interface_fields(self.doc, lookup)
+            .into_iter()
+            .map(|field| {
+                // Types are named inside the macro, they must be in scope.
+                self.context
+                    .import_path(field.ty.node.entity_type(), &mut scope);
                 field.interface_field_macro()
             })
             .collect::<Vec<String>>();
@@ -58,14 +117,11 @@ impl<'a> InterfaceWrapper<'a> {
             .object_types()
             .iter()
             .for_each(|x| match &x.doc.kind {
-                TypeKind::Object(object) => {
-                    let machin = object
-                        .implements
-                        .iter()
-                        .find(|name| name.node.as_str() == self.object_name())
-                        .is_some();
-
-                    if machin {
+                TypeKind::Object(_) => {
+                    let implemented =
+                        interface_ancestors(x.doc, lookup).contains(&self.object_name());
+
+                    if implemented {
                         let (path, name) = x.doc.auto_import_path().unwrap();
                         scope.import(&path, &name);
 
@@ -8,6 +8,7 @@ pub mod inp;
 pub mod input;
 pub mod input_value_definition;
 pub mod interfaces;
+pub mod obj;
 pub mod object;
 pub mod scal;
 pub mod scalars;
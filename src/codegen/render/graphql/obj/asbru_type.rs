@@ -0,0 +1,97 @@
+//! ObjectExtension trait
+//! This define a trait for Object types, it'll allow us to read type-level directives which alter
+//! how every field returning this type is generated.
+
+use crate::codegen::render::graphql::directive::{
+    ServiceBackedConnectionDirective, ServiceBackedNodeDirective,
+};
+use async_graphql_parser::types::{ConstDirective, TypeDefinition};
+use async_graphql_value::ConstValue;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AsbruObjectExtErrors {
+    #[error("Argument {0} is missing")]
+    ArgumentMissingError(String),
+    #[error("Argument {0} is with the wrong type")]
+    ArgumentTypeError(String),
+}
+
+pub(crate) trait AsbruObjectExt {
+    /// Get the `@serviceBackedNode` directive of this type.
+    /// If the directive is invalid, it'll result in an error.
+    fn service_backed_node(
+        &self,
+    ) -> Result<Option<ServiceBackedNodeDirective>, AsbruObjectExtErrors>;
+
+    /// Get the `@serviceBackedConnection` directive of this type.
+    /// If the directive is invalid, it'll result in an error.
+    fn service_backed_connection(
+        &self,
+    ) -> Result<Option<ServiceBackedConnectionDirective>, AsbruObjectExtErrors>;
+}
+
+/// Get a String argument from a directive.
+fn string_argument(directive: &ConstDirective, name: &str) -> Result<String, AsbruObjectExtErrors> {
+    match directive.get_argument(name).map(|x| &x.node) {
+        Some(ConstValue::String(value)) => Ok(value.to_owned()),
+        None => Err(AsbruObjectExtErrors::ArgumentMissingError(name.to_string())),
+        _ => Err(AsbruObjectExtErrors::ArgumentTypeError(name.to_string())),
+    }
+}
+
+/// Get an optional String argument from a directive.
+fn optional_string_argument(
+    directive: &ConstDirective,
+    name: &str,
+) -> Result<Option<String>, AsbruObjectExtErrors> {
+    match directive.get_argument(name).map(|x| &x.node) {
+        Some(ConstValue::String(value)) => Ok(Some(value.to_owned())),
+        None | Some(ConstValue::Null) => Ok(None),
+        _ => Err(AsbruObjectExtErrors::ArgumentTypeError(name.to_string())),
+    }
+}
+
+impl AsbruObjectExt for TypeDefinition {
+    fn service_backed_node(
+        &self,
+    ) -> Result<Option<ServiceBackedNodeDirective>, AsbruObjectExtErrors> {
+        let directive = match self
+            .directives
+            .iter()
+            .find(|x| x.node.name.node.as_str() == "serviceBackedNode")
+        {
+            Some(directive) => &directive.node,
+            None => return Ok(None),
+        };
+
+        Ok(Some(ServiceBackedNodeDirective {
+            node: self.name.node.as_str().to_owned(),
+            service: string_argument(directive, "service")?,
+            method_name: string_argument(directive, "methodName")?,
+        }))
+    }
+
+    fn service_backed_connection(
+        &self,
+    ) -> Result<Option<ServiceBackedConnectionDirective>, AsbruObjectExtErrors> {
+        let directive = match self
+            .directives
+            .iter()
+            .find(|x| x.node.name.node.as_str() == "serviceBackedConnection")
+        {
+            Some(directive) => &directive.node,
+            None => return Ok(None),
+        };
+
+        Ok(Some(ServiceBackedConnectionDirective {
+            connection: self.name.node.as_str().to_owned(),
+            service: string_argument(directive, "service")?,
+            method_name: string_argument(directive, "methodName")?,
+            source_id_request_field_name: optional_string_argument(
+                directive,
+                "sourceIdRequestFieldName",
+            )?,
+        }))
+    }
+}
@@ -0,0 +1 @@
+pub mod asbru_type;
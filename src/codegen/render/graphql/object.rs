@@ -1,11 +1,12 @@
+use crate::codegen::context::regions::user_region;
 use crate::codegen::render::graphql::field::FieldDefinitionExt;
-use crate::codegen::render::graphql::scal::asbru_type::AsbruType;
+use crate::codegen::render::graphql::obj::asbru_type::AsbruObjectExt;
+use crate::codegen::render::graphql::scal::asbru_type::{AsbruType, AsbruTypeErrors};
 use crate::codegen::{context::Context, generate::GenericErrors, render::render::Render};
 use async_graphql_parser::types::{FieldDefinition, TypeDefinition, TypeKind};
+use async_graphql_parser::Positioned;
 use codegen::{Impl, Scope, Struct};
 
-const DENYLIST: [&str; 2] = ["Mutation", "Subscription"];
-
 pub struct ObjectWrapper<'a> {
     // We store the whole type definition because we might need directives but it's an object, we
     // should refine this type later.
@@ -31,6 +32,19 @@ impl<'a> ObjectWrapper<'a> {
         }
     }
 
+    /// Attach the position of a field to the error raised while generating it.
+    fn located_error(
+        &self,
+        field: &Positioned<FieldDefinition>,
+        error: AsbruTypeErrors,
+    ) -> AsbruTypeErrors {
+        AsbruTypeErrors::LocatedError(
+            format!("{}.{}", self.object_name(), field.node.name.node),
+            field.pos,
+            Box::new(error),
+        )
+    }
+
     /// Generate a domain file for the actual type.
     /// We create a representation for each fields with no arguments and no directive.
     pub fn generate_domain_file(&self) -> Result<(), GenericErrors> {
@@ -56,11 +70,17 @@ impl<'a> ObjectWrapper<'a> {
             }
         }
         .iter()
-        .for_each(|x| {
+        .try_for_each(|x| {
             x.node
-                .struct_field_builder(&self.context, &mut scope, &mut object_struct)
-                .unwrap();
-        });
+                .struct_field_builder(
+                    &self.context,
+                    self.object_name(),
+                    &mut scope,
+                    &mut object_struct,
+                )
+                .map(|_| ())
+                .map_err(|e| self.located_error(x, e))
+        })?;
 
         scope.push_struct(object_struct);
 
@@ -80,6 +100,8 @@ impl<'a> ObjectWrapper<'a> {
             &format!("crate::domain::{}", self.object_name().to_lowercase()),
             self.object_name(),
         );
+        // Imports of the hand-written code are kept between runs.
+        scope.raw(&user_region("use", ""));
 
         let mut impl_struct = Impl::new(self.object_name());
         impl_struct.r#macro("#[Object]");
@@ -94,8 +116,71 @@ impl<'a> ObjectWrapper<'a> {
         .iter()
         .try_for_each(|x| {
             x.node
-                .function_field_builder(&self.context, &mut scope, &mut impl_struct)
+                .function_field_builder(
+                    &self.context,
+                    self.object_name(),
+                    &mut scope,
+                    &mut impl_struct,
+                )
                 .map(|_| ())
+                .map_err(|e| self.located_error(x, e))
+        })?;
+
+        scope.push_impl(impl_struct);
+
+        self.context.create_a_new_file(
+            format!("application/{}", &self.domain_name()),
+            scope.to_string().as_bytes(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Generate the domain and the application files for the Subscription type.
+    pub fn generate_subscription_file(&self) -> Result<(), GenericErrors> {
+        let mut domain_scope = Scope::new();
+        let mut object_struct = Struct::new(self.object_name());
+        object_struct
+            .vis("pub")
+            .derive("Debug")
+            .derive("Default")
+            .derive("Clone");
+        domain_scope.push_struct(object_struct);
+
+        self.context.create_a_new_file(
+            format!("domain/{}", &self.domain_name()),
+            domain_scope.to_string().as_bytes(),
+        )?;
+
+        let mut scope = Scope::new();
+        scope.import("async_graphql", "*");
+        scope.import(
+            &format!("crate::domain::{}", self.object_name().to_lowercase()),
+            self.object_name(),
+        );
+        // Imports of the hand-written code are kept between runs.
+        scope.raw(&user_region("use", ""));
+
+        let mut impl_struct = Impl::new(self.object_name());
+        impl_struct.r#macro("#[Subscription]");
+
+        match &self.doc.kind {
+            TypeKind::Object(object) => &object.fields,
+            _ => {
+                return Err(GenericErrors::GenericGeneratorError);
+            }
+        }
+        .iter()
+        .try_for_each(|x| {
+            x.node
+                .subscription_field_builder(
+                    &self.context,
+                    self.object_name(),
+                    &mut scope,
+                    &mut impl_struct,
+                )
+                .map(|_| ())
+                .map_err(|e| self.located_error(x, e))
         })?;
 
         scope.push_impl(impl_struct);
@@ -112,22 +197,18 @@ impl<'a> ObjectWrapper<'a> {
 impl<'a> Render for ObjectWrapper<'a> {
     fn generate(&self) -> Result<(), GenericErrors> {
         let object_name = self.doc.name.node.as_str();
-        // Hacky denylist.
-        if DENYLIST.iter().find(|name| **name == object_name).is_some() {
-            return Ok(());
+
+        // Subscription fields are streams, there is nothing to store inside the domain.
+        if object_name == "Subscription" {
+            return self.generate_subscription_file();
         };
 
-        // If content is connection or Payload, we do not create the normal process: We do not need
-        // to create a domain file, we just need the parent resolver to return a Connection element
-        // from `async-graphql`.
+        // If content is connection, we do not create the normal process: We do not need to create
+        // a domain file, we just need the parent resolver to return a Connection element from
+        // `async-graphql`.
         //
+        // Payloads are simple entities: the response of a mutation is mapped into it.
         if object_name.ends_with("Connection") || object_name.ends_with("Edge") {
-            // todo
-            return Ok(());
-        };
-
-        if object_name.ends_with("Payload") {
-            // todo
             return Ok(());
         };
 
@@ -136,6 +217,10 @@ impl<'a> Render for ObjectWrapper<'a> {
         self.generate_domain_file()?;
         self.generate_application_file()?;
 
+        if let Some(node) = self.doc.service_backed_node()? {
+            node.generate_loader_file(self.context)?;
+        }
+
         // Create files
         Ok(())
     }
@@ -2,16 +2,22 @@
 //! Define a specialized type which will be used to generate associated functions and definition in
 //! Asbru.
 //! This trait should be applied to Field
-use async_graphql_parser::types::{BaseType, FieldDefinition, Type};
+use async_graphql_parser::{
+    types::{BaseType, FieldDefinition, Type},
+    Pos,
+};
 use codegen::{Field, Function, Impl, Scope, Struct};
 use thiserror::Error;
 
 use crate::codegen::{
-    context::Context,
+    context::{regions::user_region, Context},
+    generate::GenericErrors,
     render::graphql::{
+        directive::ScopeDirective,
         fie::asbru_type::{AsbruFieldExt, AsbruFieldExtErrors},
         field::FieldDefinitionExt,
         inp::AsbruInputValue,
+        obj::asbru_type::AsbruObjectExtErrors,
         scalars::ToRustType,
     },
 };
@@ -23,8 +29,17 @@ pub enum AsbruTypeErrors {
     NoConnectionItemError,
     #[error("A connection entity should have edges")]
     NoEdgesItemError,
-    #[error("Field directive errors")]
+    #[error(transparent)]
     FieldDirectivesError(#[from] AsbruFieldExtErrors),
+    #[error(transparent)]
+    ObjectDirectivesError(#[from] AsbruObjectExtErrors),
+    #[error("Nested lists of nodes are not supported yet: {0}")]
+    NestedListNodeError(String),
+    #[error(transparent)]
+    GeneratorError(#[from] Box<GenericErrors>),
+    /// An error raised while generating a field or a value, with its position in the schema.
+    #[error("{0}: {2}")]
+    LocatedError(String, Pos, Box<AsbruTypeErrors>),
 }
 
 pub(crate) trait AsbruType {
@@ -65,9 +80,12 @@ pub(crate) trait AsbruType {
     ///   - Enum -> Enum Representation
     ///   - Node -> Either id or Node.
     ///
+    /// `parent` is the name of the type owning the field, a field closing a cycle between types
+    /// is boxed.
     fn struct_field_builder<'a, 'b>(
         &self,
         context: &'a Context,
+        parent: &str,
         scope: &mut Scope,
         domain_struct: &'b mut Struct,
     ) -> Result<&'b mut Struct, AsbruTypeErrors>;
@@ -78,10 +96,22 @@ pub(crate) trait AsbruType {
     ///
     /// Depending of the Field and the associated directive, it'll create the function definition.
     ///
-    /// Field directives and data directives can alter this codegen.
+    /// Field directives and data directives can alter this codegen, like the scope directives
+    /// of the field and of its `parent` type.
     fn function_field_builder<'a, 'b>(
         &self,
         context: &'a Context,
+        parent: &str,
+        scope: &mut Scope,
+        graphql_impl: &'b mut Impl,
+    ) -> Result<&'b mut Impl, AsbruTypeErrors>;
+
+    /// Generate an associated function for a GraphQL Field of the Subscription type.
+    /// The function returns a `Stream` of the field type instead of the field type.
+    fn subscription_field_builder<'a, 'b>(
+        &self,
+        context: &'a Context,
+        parent: &str,
         scope: &mut Scope,
         graphql_impl: &'b mut Impl,
     ) -> Result<&'b mut Impl, AsbruTypeErrors>;
@@ -130,9 +160,9 @@ impl ConnectionData {
         &self,
         context: &'a Context,
         scope: &'b mut Scope,
-    ) -> String {
+    ) -> Result<String, AsbruTypeErrors> {
         if self.addition_field_for_edge.len() == 0 {
-            return self.edges_fields_name();
+            return Ok(self.edges_fields_name());
         }
 
         scope.import("serde", "Serialize");
@@ -150,16 +180,13 @@ impl ConnectionData {
             .derive("Default")
             .derive("Clone");
 
-        self.addition_field_for_edge
-            .iter()
-            .try_for_each(|x| {
-                x.struct_field_builder(context, scope, &mut additional_edges_fields)
-                    .map(|_| ())
-            })
-            .unwrap();
+        self.addition_field_for_edge.iter().try_for_each(|x| {
+            x.struct_field_builder(context, &name, scope, &mut additional_edges_fields)
+                .map(|_| ())
+        })?;
 
         scope.push_struct(additional_edges_fields);
-        name
+        Ok(name)
     }
 
     /// Give the struct name and Generate it into the scope.
@@ -167,9 +194,9 @@ impl ConnectionData {
         &self,
         context: &'a Context,
         scope: &'b mut Scope,
-    ) -> String {
+    ) -> Result<String, AsbruTypeErrors> {
         if self.addition_field_for_node.len() == 0 {
-            return self.node_fields_name();
+            return Ok(self.node_fields_name());
         }
         scope.import("serde", "Serialize");
         scope.import("serde", "Deserialize");
@@ -186,16 +213,13 @@ impl ConnectionData {
             .derive("Default")
             .derive("Clone");
 
-        self.addition_field_for_node
-            .iter()
-            .try_for_each(|x| {
-                x.struct_field_builder(context, scope, &mut additional_node_fields)
-                    .map(|_| ())
-            })
-            .unwrap();
+        self.addition_field_for_node.iter().try_for_each(|x| {
+            x.struct_field_builder(context, &name, scope, &mut additional_node_fields)
+                .map(|_| ())
+        })?;
 
         scope.push_struct(additional_node_fields);
-        name
+        Ok(name)
     }
 }
 /// When we create a connection, we have to generate more structure than other stuff:
@@ -318,6 +342,7 @@ pub enum GraphQLType {
     NativeType,
     EnumType,
     ConnectionType,
+    NodeType,
     UnknownType,
 }
 
@@ -331,10 +356,14 @@ fn graphql_type<'a>(type_gql: &Type, context: &'a Context) -> GraphQLType {
             "Float" => GraphQLType::NativeType,
             "ID" => GraphQLType::NativeType,
             _ => {
-                if name.as_str().ends_with("Connection") {
+                if context.is_scalar(name.as_str()) {
+                    GraphQLType::NativeType
+                } else if name.as_str().ends_with("Connection") {
                     GraphQLType::ConnectionType
                 } else if context.is_enum(name.as_str()) {
                     GraphQLType::EnumType
+                } else if context.is_service_backed_node(name.as_str()) {
+                    GraphQLType::NodeType
                 } else {
                     GraphQLType::UnknownType
                 }
@@ -344,6 +373,29 @@ fn graphql_type<'a>(type_gql: &Type, context: &'a Context) -> GraphQLType {
     }
 }
 
+/// Add the arguments of a GraphQL Field to its resolver, importing their types when needed.
+fn resolver_arguments<'a>(
+    context: &'a Context,
+    field: &FieldDefinition,
+    scope: &mut Scope,
+    function: &mut Function,
+) -> Result<(), AsbruTypeErrors> {
+    for argument in field.arguments.iter() {
+        // Enums and inputs used as arguments must be imported.
+        context.import_path(argument.node.ty.node.entity_type(), scope);
+        function.arg(
+            &format!(
+                "{} {}",
+                argument.node.formatted_macro(),
+                argument.node.name.node.as_str().to_case(Case::Snake)
+            ),
+            &to_rust_type_name(context, &argument.node.ty.node, None)?,
+        );
+    }
+
+    Ok(())
+}
+
 impl AsbruType for FieldDefinition {
     fn name(&self) -> &str {
         self.name.node.as_str()
@@ -363,6 +415,7 @@ impl AsbruType for FieldDefinition {
     fn struct_field_builder<'a, 'b>(
         &self,
         context: &'a Context,
+        parent: &str,
         scope: &mut Scope,
         domain_struct: &'b mut Struct,
     ) -> Result<&'b mut Struct, AsbruTypeErrors> {
@@ -376,6 +429,11 @@ impl AsbruType for FieldDefinition {
             return Ok(domain_struct);
         }
 
+        // Derived fields are computed from the domain, they are not part of it.
+        if self.derived_field()?.is_some() {
+            return Ok(domain_struct);
+        }
+
         let return_type = to_rust_type_name(
             context,
             &self.ty.node,
@@ -384,11 +442,14 @@ impl AsbruType for FieldDefinition {
 
         let opt_key = self
             .key_directive()?
-            .map(|x| format!("#[serde(alias = \"{}\")]\n", x.key))
+            .map(|x| x.field_attributes(&self.name().to_case(Case::Snake), scope))
             .unwrap_or("".to_string());
 
         match graphql_type(&self.ty.node, context) {
             GraphQLType::NativeType => {
+                // Custom scalars are newtypes generated inside the domain.
+                context.import_path(self.entity_type(), scope);
+
                 let mut field = Field::new(
                     &format!("{}pub {}", &opt_key, self.name().to_case(Case::Snake)),
                     match &*return_type {
@@ -427,18 +488,45 @@ impl AsbruType for FieldDefinition {
 
                 Ok(domain_struct.push_field(field))
             }
-            // Now depending if it's a Node with a backedNode directive or not, we should maybe
-            // manage things differently
-            // With a backedNode, we only need an id.
+            // With a backedNode, we only need an id, the node will be loaded with a DataLoader.
+            GraphQLType::NodeType => {
+                let id_type =
+                    to_rust_type_name(context, &self.ty.node, Some("String".to_string()))?;
+
+                let mut field = Field::new(
+                    &format!("{}pub {}_id", &opt_key, self.name().to_case(Case::Snake)),
+                    id_type,
+                );
+
+                // Ugly but it'll work right now.
+                field.doc(vec![&self
+                    .description
+                    .clone()
+                    .map(|x| x.node.as_str().to_string())
+                    .unwrap_or("".to_string())]);
+
+                Ok(domain_struct.push_field(field))
+            }
             // Without a backedNode, we need to have the full model
             //
-            // We do not support recursive patterns yet coz it would need a Box
+            // A model embedding itself, directly or through other models, would be infinitely
+            // sized: the field closing the cycle is boxed.
             _ => {
                 scope.import(
                     &format!("crate::domain::{}", self.entity_type().to_lowercase()),
                     &self.entity_type(),
                 );
 
+                let return_type = if context.is_recursive_field(parent, self.name()) {
+                    to_rust_type_name(
+                        context,
+                        &self.ty.node,
+                        Some(format!("Box<{}>", self.entity_type())),
+                    )?
+                } else {
+                    return_type
+                };
+
                 let mut field = Field::new(
                     &format!("{}pub {}", &opt_key, self.name().to_case(Case::Snake)),
                     return_type,
@@ -460,6 +548,7 @@ impl AsbruType for FieldDefinition {
     fn function_field_builder<'a, 'b>(
         &self,
         context: &'a Context,
+        parent: &str,
         scope: &mut Scope,
         graphql_impl: &'b mut Impl,
     ) -> Result<&'b mut Impl, AsbruTypeErrors> {
@@ -469,6 +558,10 @@ impl AsbruType for FieldDefinition {
         // - It's a Query with associated directives.
 
         let return_type = to_rust_type_name(context, &self.ty.node, None)?;
+        let field_type = graphql_type(&self.ty.node, context);
+        let service_backed_query = self.service_backed_query()?;
+        let service_backed_mutation = self.service_backed_mutation()?;
+        let service_backed = service_backed_query.is_some() || service_backed_mutation.is_some();
 
         let mut resolver_fct = Function::new(&self.name().to_case(Case::Snake));
         resolver_fct
@@ -483,19 +576,38 @@ impl AsbruType for FieldDefinition {
             )
             .arg_ref_self();
 
-        for argument in self.arguments.iter() {
-            resolver_fct.arg(
-                &format!(
-                    "{} {}",
-                    argument.node.formatted_macro(),
-                    argument.node.name.node.as_str()
-                ),
-                &to_rust_type_name(context, &argument.node.ty.node, None)?,
-            );
+        if let Some(directive) =
+            ScopeDirective::required(context.type_definition_by_name(parent), self)
+        {
+            directive.generate_guard(scope, &mut resolver_fct);
+        }
+
+        // Derived fields are computed by their provider whatever their type.
+        if let Some(directive) = self.derived_field()? {
+            context.import_path(self.entity_type(), scope);
+            resolver_fct.arg("ctx", "&Context<'_>");
+            resolver_arguments(context, &self, scope, &mut resolver_fct)?;
+            directive.generate_method_definition(&self, &return_type, &mut resolver_fct);
+
+            return Ok(graphql_impl.push_fn(resolver_fct));
+        }
+
+        // Nodes are loaded with a DataLoader, and services are called with their client, both
+        // stored inside the context.
+        let service_backed_connection = field_type == GraphQLType::ConnectionType
+            && context
+                .service_backed_connection(&self.entity_type())?
+                .is_some();
+        if field_type == GraphQLType::NodeType || service_backed || service_backed_connection {
+            resolver_fct.arg("ctx", "&Context<'_>");
         }
 
-        let _ = match graphql_type(&self.ty.node, context) {
+        resolver_arguments(context, &self, scope, &mut resolver_fct)?;
+
+        let _ = match field_type {
             GraphQLType::NativeType => {
+                context.import_path(self.entity_type(), scope);
+
                 match &*return_type {
                     "String" => resolver_fct
                         .line(format!("&self.{}", self.name().to_case(Case::Snake)))
@@ -523,8 +635,9 @@ impl AsbruType for FieldDefinition {
             }
             GraphQLType::ConnectionType => {
                 let connection_data = connection_data(context, &self.ty.node)?;
-                let _name = connection_data.generate_struct_edges_fields(context, scope);
-                let _add_node_name = connection_data.generate_struct_node_fields(context, scope);
+                let connection_fields =
+                    connection_data.generate_struct_edges_fields(context, scope)?;
+                let edge_fields = connection_data.generate_struct_node_fields(context, scope)?;
 
                 scope.import(
                     &format!(
@@ -534,7 +647,52 @@ impl AsbruType for FieldDefinition {
                     &connection_data.node_field.entity_type(),
                 );
                 scope.import("async_graphql::connection", "*");
-                resolver_fct.line("todo!(\"Connection\")").ret(return_type);
+
+                match context.service_backed_connection(&self.entity_type())? {
+                    Some(directive) => {
+                        directive
+                            .generate_field_definition(
+                                context,
+                                &self,
+                                &connection_data.node_field.to_gql_rust_type(context)?,
+                                Some(&connection_fields)
+                                    .filter(|_| !connection_data.addition_field_for_edge.is_empty())
+                                    .map(|x| x.as_str()),
+                                Some(&edge_fields)
+                                    .filter(|_| !connection_data.addition_field_for_node.is_empty())
+                                    .map(|x| x.as_str()),
+                                &return_type,
+                                scope,
+                                &mut resolver_fct,
+                            )
+                            .map_err(Box::new)?;
+                    }
+                    None => {
+                        resolver_fct
+                            .line(user_region(
+                                &self.name().to_case(Case::Snake),
+                                "todo!(\"Connection\")",
+                            ))
+                            .ret(return_type);
+                    }
+                };
+            }
+            // If not a query, we load the node id stored inside the domain with the DataLoader.
+            GraphQLType::NodeType if !service_backed => {
+                scope.import(
+                    &format!("crate::domain::{}", &self.entity_type().to_lowercase()),
+                    &self.entity_type(),
+                );
+
+                if let Some(directive) = context.service_backed_node(&self.entity_type())? {
+                    directive.generate_field_definition(
+                        context,
+                        &self,
+                        &return_type,
+                        scope,
+                        &mut resolver_fct,
+                    )?;
+                }
             }
             // Depending of the directives applied, should process the field/query according to it.
             // If not a query, should dataload, if query, should have a serviceBackedQuery and use it
@@ -545,18 +703,23 @@ impl AsbruType for FieldDefinition {
                     &self.entity_type(),
                 );
 
-                match self.service_backed_query() {
-                    Some(directive) => {
-                        directive.generate_method_definition(
-                            context,
-                            &self,
-                            scope,
-                            &mut resolver_fct,
-                        );
+                match (service_backed_query, service_backed_mutation) {
+                    (Some(directive), _) => {
+                        directive
+                            .generate_method_definition(context, &self, scope, &mut resolver_fct)
+                            .map_err(Box::new)?;
                     }
-                    None => {
+                    (None, Some(directive)) => {
+                        directive
+                            .generate_method_definition(context, &self, scope, &mut resolver_fct)
+                            .map_err(Box::new)?;
+                    }
+                    (None, None) => {
                         resolver_fct
-                            .line(format!("todo!(\"WIP\")"))
+                            .line(user_region(
+                                &self.name().to_case(Case::Snake),
+                                "todo!(\"WIP\")",
+                            ))
                             .ret(format!("FieldResult<{}>", return_type));
                     }
                 };
@@ -566,6 +729,77 @@ impl AsbruType for FieldDefinition {
         Ok(graphql_impl.push_fn(resolver_fct))
     }
 
+    fn subscription_field_builder<'a, 'b>(
+        &self,
+        context: &'a Context,
+        parent: &str,
+        scope: &mut Scope,
+        graphql_impl: &'b mut Impl,
+    ) -> Result<&'b mut Impl, AsbruTypeErrors> {
+        let return_type = to_rust_type_name(context, &self.ty.node, None)?;
+
+        let mut resolver_fct = Function::new(&self.name().to_case(Case::Snake));
+        resolver_fct
+            .vis("pub")
+            .set_async(true)
+            .doc(
+                &self
+                    .description
+                    .as_ref()
+                    .map(|x| x.node.as_ref())
+                    .unwrap_or(""),
+            )
+            .arg_ref_self();
+
+        if let Some(directive) =
+            ScopeDirective::required(context.type_definition_by_name(parent), self)
+        {
+            directive.generate_guard(scope, &mut resolver_fct);
+        }
+
+        // The client of the service is stored inside the context.
+        let service_backed_subscription = self.service_backed_subscription()?;
+        if service_backed_subscription.is_some() {
+            resolver_fct.arg("ctx", "&Context<'_>");
+        }
+
+        resolver_arguments(context, &self, scope, &mut resolver_fct)?;
+        context.import_path(self.entity_type(), scope);
+
+        match service_backed_subscription {
+            Some(directive) => {
+                directive
+                    .generate_method_definition(
+                        context,
+                        &self,
+                        &return_type,
+                        scope,
+                        &mut resolver_fct,
+                    )
+                    .map_err(Box::new)?;
+            }
+            None => {
+                scope.import("futures", "Stream");
+                resolver_fct
+                    .line(user_region(
+                        &self.name().to_case(Case::Snake),
+                        &format!(
+                            r#"
+    futures::stream::once(async {{
+        let value: FieldResult<{}> = todo!("WIP");
+        value
+    }})
+                "#,
+                            return_type
+                        ),
+                    ))
+                    .ret(format!("impl Stream<Item = FieldResult<{}>>", return_type));
+            }
+        };
+
+        Ok(graphql_impl.push_fn(resolver_fct))
+    }
+
     fn entity_type(&self) -> String {
         self.ty.node.entity_type()
     }
@@ -72,7 +72,9 @@ impl ToRustType for Type {
                 "Float" => GraphQLType::NativeType,
                 "ID" => GraphQLType::NativeType,
                 _ => {
-                    if name.as_str().ends_with("Connection") {
+                    if context.is_scalar(name.as_str()) {
+                        GraphQLType::NativeType
+                    } else if name.as_str().ends_with("Connection") {
                         GraphQLType::ConnectionType
                     } else if context.is_enum(name.as_str()) {
                         GraphQLType::EnumType
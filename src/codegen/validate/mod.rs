@@ -0,0 +1,1134 @@
+//! Validation
+//! Check a schema against its config without generating anything. Every problem found is
+//! reported as a diagnostic located inside the schema or the config, instead of stopping at the
+//! first one.
+use crate::codegen::config::{
+    ArgumentSource, ArgumentsMapping, Config, ErrorMapping, Method, Transport, PAGINATION_ARGUMENTS,
+};
+use crate::codegen::context::recursion::recursive_fields;
+use crate::codegen::diagnostic::{Diagnostic, SourceFile};
+use crate::codegen::generate::{load_config, load_schema, GenericErrors};
+use crate::codegen::render::graphql::directive::SCOPE_DIRECTIVES;
+use crate::codegen::render::graphql::interfaces::interface_ancestors;
+use crate::codegen::render::graphql::scalars::ToRustType;
+use async_graphql_parser::types::{
+    BaseType, ConstDirective, FieldDefinition, InputValueDefinition, ServiceDocument, Type,
+    TypeDefinition, TypeKind, TypeSystemDefinition,
+};
+use async_graphql_parser::{Pos, Positioned};
+use async_graphql_value::ConstValue;
+use convert_case::{Case, Casing};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+/// Data directives applied on fields.
+const FIELD_DATA_DIRECTIVES: [&str; 3] = [
+    "serviceBackedQuery",
+    "serviceBackedMutation",
+    "serviceBackedSubscription",
+];
+
+/// Types available without being defined inside the schema.
+const BUILTIN_TYPES: [&str; 6] = ["String", "Int", "Float", "Boolean", "Bool", "ID"];
+
+#[derive(Error, Debug)]
+pub enum ValidationErrors {
+    #[error("{0}: service {1} not found")]
+    ServiceNotFoundError(String, String),
+    #[error("{0}: method {2} not found in service {1}")]
+    MethodNotFoundError(String, String, String),
+    #[error("{0}: method {2} of service {1} has no stream configured to back a subscription")]
+    MethodNotStreamableError(String, String, String),
+    #[error("{0}: method {2} of service {1} must have only one argument to back a node")]
+    InvalidNodeMethodError(String, String, String),
+    #[error("{0}: method {2} of service {1} can't load a batch of nodes, {3}")]
+    InvalidBatchMethodError(String, String, String, String),
+    #[error("{0}: argument {1} of method {2} matches no GraphQL argument")]
+    UnmatchedArgumentError(String, String, String),
+    #[error("{0}: argument {1} is mapped to {2} which doesn't exist")]
+    UnknownArgumentSourceError(String, String, String),
+    #[error("{0}: @{1} is malformed, {2}")]
+    MalformedDirectiveError(String, String, String),
+    #[error("{0}: type {1} is not defined")]
+    UnknownTypeError(String, String),
+    #[error("{0}: {1} is not an interface")]
+    NotAnInterfaceError(String, String),
+    #[error("{0}: field {1} of the interface {2} is missing")]
+    MissingInterfaceFieldError(String, String, String),
+    #[error("{0}: the interface implements itself")]
+    CyclicInterfaceError(String),
+    #[error("{0}: {1} are not supported yet")]
+    UnsupportedError(String, String),
+    #[error("Service {0} is not used by any directive")]
+    UnusedServiceError(String),
+    #[error("{0}.{1} is recursive, it's boxed inside the domain")]
+    BoxedRecursiveField(String, String),
+    #[error("Scalar {0} has no Rust type inside the [scalars] config, it's exchanged as a String")]
+    UnmappedScalarError(String),
+    #[error("{0}: @{1} requires the [auth] config")]
+    MissingAuthConfigError(String, String),
+    #[error("{0}: {1} is not a HTTP status, its error code can't be mapped")]
+    InvalidStatusError(String, String),
+    #[error("{0}: the circuit breaker must open after at least one failure")]
+    InvalidBreakerError(String),
+    #[error("{0}: {1} calls are not idempotent, they are not retried")]
+    NotRetriedError(String, String),
+    #[error("{0}: {1} is fetched with the id of its parent, but {2} has no id field")]
+    MissingSourceIdError(String, String, String),
+}
+
+/// Validate the schema at `path` against the config at `config`.
+/// Files which can't be read or parsed are errors, problems found are returned.
+pub fn validate<P: AsRef<Path>>(path: P, config: P) -> Result<Vec<Diagnostic>, GenericErrors> {
+    let (schema_file, schema) = load_schema(&path)?;
+    let (config_file, config) = load_config(&config)?;
+
+    Ok(check(&schema_file, &schema, &config_file, &config))
+}
+
+/// Check a parsed schema against a parsed config, diagnostics are located inside their files.
+pub(crate) fn check(
+    schema_file: &SourceFile,
+    schema: &ServiceDocument,
+    config_file: &SourceFile,
+    config: &Config,
+) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        schema_file,
+        schema,
+        config_file,
+        config,
+        diagnostics: Vec::new(),
+        used_services: HashSet::new(),
+    };
+    validator.validate();
+
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    schema_file: &'a SourceFile,
+    schema: &'a ServiceDocument,
+    config_file: &'a SourceFile,
+    config: &'a Config,
+    diagnostics: Vec<Diagnostic>,
+    used_services: HashSet<String>,
+}
+
+impl<'a> Validator<'a> {
+    fn validate(&mut self) {
+        for type_def in self.type_definitions() {
+            let type_name = type_def.node.name.node.as_str();
+
+            if type_def.node.extend {
+                self.report(
+                    type_def.pos,
+                    ValidationErrors::UnsupportedError(
+                        type_name.to_string(),
+                        "type extensions".to_string(),
+                    ),
+                );
+            }
+
+            self.validate_type_directives(&type_def.node);
+            self.validate_scope_directives(type_name, &type_def.node.directives);
+            self.validate_implements(type_def);
+
+            match &type_def.node.kind {
+                TypeKind::Object(object) => object
+                    .fields
+                    .iter()
+                    .for_each(|x| self.validate_field(type_name, x)),
+                TypeKind::Interface(interface) => interface
+                    .fields
+                    .iter()
+                    .for_each(|x| self.validate_field(type_name, x)),
+                TypeKind::InputObject(input) => {
+                    for field in input.fields.iter() {
+                        let location = format!("{}.{}", type_name, field.node.name.node);
+                        self.validate_type(&location, &field.node.ty);
+                    }
+                }
+                TypeKind::Enum(enum_type) => {
+                    for value in enum_type.values.iter() {
+                        let location = format!("{}.{}", type_name, value.node.value.node);
+                        for directive in value.node.directives.iter() {
+                            if directive.node.name.node.as_str() == "rename" {
+                                self.string_argument(&location, directive, "name");
+                            }
+                        }
+                    }
+                }
+                TypeKind::Scalar => {
+                    if self.config.scalar_type(type_name).is_none() {
+                        self.diagnostics.push(
+                            Diagnostic::warning(ValidationErrors::UnmappedScalarError(
+                                type_name.to_string(),
+                            ))
+                            .at(
+                                self.schema_file,
+                                type_def.pos.line,
+                                type_def.pos.column,
+                            ),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.report_recursive_fields();
+
+        let config = self.config;
+        let mut unused = config
+            .services()
+            .keys()
+            .filter(|x| !self.used_services.contains(*x))
+            .collect::<Vec<&String>>();
+        unused.sort();
+        for service in unused {
+            let diagnostic =
+                Diagnostic::warning(ValidationErrors::UnusedServiceError(service.to_string()));
+            self.report_config(service, diagnostic);
+        }
+
+        self.validate_error_mappings();
+        self.validate_resilience();
+        self.validate_protos();
+    }
+
+    /// Report a diagnostic located at the config of a service.
+    fn report_config(&mut self, service: &str, diagnostic: Diagnostic) {
+        let diagnostic = match self.config_file.find_line(&format!("services.{}", service)) {
+            Some(line) => diagnostic.at(self.config_file, line, 1),
+            None => diagnostic,
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Statuses mapped to an error code, by a HTTP service or one of its methods, must be valid
+    /// HTTP statuses.
+    fn validate_error_mappings(&mut self) {
+        let config = self.config;
+        let mut services = config.services().iter().collect::<Vec<_>>();
+        services.sort_by_key(|(name, _)| *name);
+
+        for (service_name, service) in services {
+            let http = match service.transport() {
+                Transport::HTTP(http) => http,
+                Transport::GRPC(_) => continue,
+            };
+
+            let mut methods = http.methods().iter().collect::<Vec<_>>();
+            methods.sort_by_key(|(name, _)| *name);
+            let mut mappings: Vec<(String, &ErrorMapping)> =
+                vec![(service_name.to_string(), http.errors())];
+            mappings.extend(methods.into_iter().filter_map(|(name, method)| {
+                method
+                    .errors()
+                    .map(|x| (format!("{}.{}", service_name, name), x))
+            }));
+
+            for (location, mapping) in mappings {
+                for status in mapping.invalid_statuses() {
+                    let diagnostic = Diagnostic::error(ValidationErrors::InvalidStatusError(
+                        location.clone(),
+                        status.to_string(),
+                    ));
+                    self.report_config(service_name, diagnostic);
+                }
+            }
+        }
+    }
+
+    /// Circuit breakers must open after a failure, and retries configured on calls which are
+    /// not idempotent are reported as they are ignored.
+    fn validate_resilience(&mut self) {
+        let config = self.config;
+        let mut services = config.services().iter().collect::<Vec<_>>();
+        services.sort_by_key(|(name, _)| *name);
+
+        for (service_name, service) in services {
+            let mut resiliences = vec![(service_name.to_string(), service.resilience(), None)];
+            match service.transport() {
+                Transport::HTTP(http) => {
+                    let mut methods = http.methods().iter().collect::<Vec<_>>();
+                    methods.sort_by_key(|(name, _)| *name);
+                    resiliences.extend(methods.into_iter().filter_map(|(name, method)| {
+                        method.resilience().map(|x| {
+                            let verb = if method.is_idempotent() {
+                                None
+                            } else {
+                                Some(method.http_method_name())
+                            };
+                            (format!("{}.{}", service_name, name), x, verb)
+                        })
+                    }));
+                }
+                Transport::GRPC(_) => resiliences[0].2 = Some("gRPC"),
+            }
+
+            for (location, resilience, verb) in resiliences {
+                if matches!(&resilience.breaker, Some(breaker) if breaker.failures == 0) {
+                    let diagnostic =
+                        Diagnostic::error(ValidationErrors::InvalidBreakerError(location.clone()));
+                    self.report_config(service_name, diagnostic);
+                }
+
+                if let Some(verb) = verb.filter(|_| resilience.retries.unwrap_or(0) > 0) {
+                    let diagnostic = Diagnostic::warning(ValidationErrors::NotRetriedError(
+                        location,
+                        verb.to_string(),
+                    ));
+                    self.report_config(service_name, diagnostic);
+                }
+            }
+        }
+    }
+
+    /// Only the messages of the package of a gRPC service can be mapped through serde, the
+    /// well-known types imported by a `.proto` can't. A `.proto` which can't be read is reported
+    /// while generating.
+    fn validate_protos(&mut self) {
+        let config = self.config;
+        let mut services = config.services().iter().collect::<Vec<_>>();
+        services.sort_by_key(|(name, _)| *name);
+
+        for (service_name, service) in services {
+            let grpc = match service.transport() {
+                Transport::GRPC(grpc) => grpc,
+                Transport::HTTP(_) => continue,
+            };
+
+            let uses_well_known_types = fs::read_to_string(grpc.proto())
+                .map(|x| x.contains("google.protobuf."))
+                .unwrap_or(false);
+            if uses_well_known_types {
+                let diagnostic = Diagnostic::error(ValidationErrors::UnsupportedError(
+                    grpc.proto().to_string(),
+                    "well-known types (google.protobuf.*)".to_string(),
+                ));
+                self.report_config(service_name, diagnostic);
+            }
+        }
+    }
+
+    /// Fields closing a cycle between types are boxed, it's reported as a note.
+    fn report_recursive_fields(&mut self) {
+        let definitions = self.type_definitions();
+        let types = definitions.iter().map(|x| &x.node).collect::<Vec<_>>();
+
+        for (type_name, field_name) in recursive_fields(&types) {
+            let pos = definitions
+                .iter()
+                .filter(|x| x.node.name.node.as_str() == type_name)
+                .find_map(|x| match &x.node.kind {
+                    TypeKind::Object(object) => object
+                        .fields
+                        .iter()
+                        .find(|x| x.node.name.node.as_str() == field_name)
+                        .map(|x| x.pos),
+                    _ => None,
+                });
+
+            let note = Diagnostic::note(ValidationErrors::BoxedRecursiveField(
+                type_name.to_string(),
+                field_name.to_string(),
+            ));
+            self.diagnostics.push(match pos {
+                Some(pos) => note.at(self.schema_file, pos.line, pos.column),
+                None => note,
+            });
+        }
+    }
+
+    /// Report an error located inside the schema.
+    fn report(&mut self, pos: Pos, error: ValidationErrors) {
+        self.diagnostics
+            .push(Diagnostic::error(error).at(self.schema_file, pos.line, pos.column));
+    }
+
+    fn type_definitions(&self) -> Vec<&'a Positioned<TypeDefinition>> {
+        self.schema
+            .definitions
+            .iter()
+            .filter_map(|x| match x {
+                TypeSystemDefinition::Type(n) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn type_definition(&self, name: &str) -> Option<&'a TypeDefinition> {
+        self.type_definitions()
+            .into_iter()
+            .map(|x| &x.node)
+            .find(|x| x.name.node.as_str() == name)
+    }
+
+    /// Tell if the type has a `@serviceBackedNode` directive.
+    fn is_node(&self, name: &str) -> bool {
+        self.type_definition(name)
+            .map(|x| {
+                x.directives
+                    .iter()
+                    .any(|x| x.node.name.node.as_str() == "serviceBackedNode")
+            })
+            .unwrap_or(false)
+    }
+
+    /// Check that the type of a field or an argument is defined.
+    fn validate_type(&mut self, location: &str, ty: &Positioned<Type>) {
+        let name = match &ty.node.base {
+            BaseType::Named(name) => name.as_str().to_string(),
+            BaseType::List(_) => ty.node.entity_type(),
+        };
+        // `entity_type` maps the builtin scalars to their Rust types.
+        let name = match name.as_str() {
+            "bool" => "Boolean".to_string(),
+            "i32" => "Int".to_string(),
+            "f64" => "Float".to_string(),
+            _ => name,
+        };
+
+        if !BUILTIN_TYPES.contains(&name.as_str()) && self.type_definition(&name).is_none() {
+            self.report(
+                ty.pos,
+                ValidationErrors::UnknownTypeError(location.to_string(), name),
+            );
+        }
+    }
+
+    /// Check that the interfaces implemented by a type, directly or through other interfaces, are
+    /// interfaces and that the type has all their fields.
+    fn validate_implements(&mut self, type_def: &Positioned<TypeDefinition>) {
+        let type_name = type_def.node.name.node.as_str();
+        let fields = match &type_def.node.kind {
+            TypeKind::Object(object) => &object.fields,
+            TypeKind::Interface(interface) => &interface.fields,
+            _ => return,
+        };
+
+        let definitions = self.type_definitions();
+        let lookup = |name: &str| {
+            definitions
+                .iter()
+                .map(|x| &x.node)
+                .find(|x| x.name.node.as_str() == name)
+        };
+
+        let mut errors = Vec::new();
+        for ancestor in interface_ancestors(&type_def.node, lookup) {
+            let interface = match lookup(ancestor).map(|x| &x.kind) {
+                Some(TypeKind::Interface(interface)) => interface,
+                Some(_) => {
+                    errors.push(ValidationErrors::NotAnInterfaceError(
+                        type_name.to_string(),
+                        ancestor.to_string(),
+                    ));
+                    continue;
+                }
+                None => {
+                    errors.push(ValidationErrors::UnknownTypeError(
+                        type_name.to_string(),
+                        ancestor.to_string(),
+                    ));
+                    continue;
+                }
+            };
+
+            if ancestor == type_name {
+                errors.push(ValidationErrors::CyclicInterfaceError(
+                    type_name.to_string(),
+                ));
+                continue;
+            }
+
+            for field in interface.fields.iter() {
+                let field_name = field.node.name.node.as_str();
+                if !fields
+                    .iter()
+                    .any(|x| x.node.name.node.as_str() == field_name)
+                {
+                    errors.push(ValidationErrors::MissingInterfaceFieldError(
+                        type_name.to_string(),
+                        field_name.to_string(),
+                        ancestor.to_string(),
+                    ));
+                }
+            }
+        }
+
+        for error in errors {
+            self.report(type_def.pos, error);
+        }
+    }
+
+    /// `@serviceBackedNode` and `@serviceBackedConnection`.
+    fn validate_type_directives(&mut self, type_def: &TypeDefinition) {
+        let type_name = type_def.name.node.as_str();
+
+        for directive in type_def.directives.iter() {
+            let name = directive.node.name.node.as_str();
+            if name != "serviceBackedNode" && name != "serviceBackedConnection" {
+                continue;
+            }
+
+            if name == "serviceBackedConnection" {
+                self.optional_string_argument(type_name, directive, "sourceIdRequestFieldName");
+            }
+
+            let (service, method_name) = match self.service_method(type_name, directive) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            match self.method(type_name, directive.pos, &service, &method_name) {
+                // The nodes of a batch are loaded with a single call and matched by their id.
+                Some(method) if name == "serviceBackedNode" => {
+                    let batch = if method.argument_names().len() != 1 {
+                        Err(ValidationErrors::InvalidNodeMethodError(
+                            type_name.to_string(),
+                            service.clone(),
+                            method_name.clone(),
+                        ))
+                    } else if !self.has_field(type_name, "id") {
+                        Err(ValidationErrors::InvalidBatchMethodError(
+                            type_name.to_string(),
+                            service.clone(),
+                            method_name.clone(),
+                            format!("{} has no id field to match the nodes with", type_name),
+                        ))
+                    } else {
+                        method.batch_argument().map_err(|reason| {
+                            ValidationErrors::InvalidBatchMethodError(
+                                type_name.to_string(),
+                                service.clone(),
+                                method_name.clone(),
+                                reason,
+                            )
+                        })
+                    };
+                    if let Err(e) = batch {
+                        self.report(directive.pos, e);
+                    }
+                }
+                // Pagination arguments are optional, a route placeholder can't be.
+                Some(method) => {
+                    if method.argument_names().iter().any(|x| {
+                        PAGINATION_ARGUMENTS.contains(&x.as_str()) && method.is_route_argument(x)
+                    }) {
+                        self.report(
+                            directive.pos,
+                            ValidationErrors::UnsupportedError(
+                                type_name.to_string(),
+                                "pagination arguments inside the route".to_string(),
+                            ),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn validate_field(&mut self, type_name: &str, field: &Positioned<FieldDefinition>) {
+        let location = format!("{}.{}", type_name, field.node.name.node);
+
+        self.validate_type(&location, &field.node.ty);
+        for argument in field.node.arguments.iter() {
+            self.validate_type(
+                &format!("{}({})", location, argument.node.name.node),
+                &argument.node.ty,
+            );
+        }
+
+        let data_directives = field
+            .node
+            .directives
+            .iter()
+            .filter(|x| FIELD_DATA_DIRECTIVES.contains(&x.node.name.node.as_str()))
+            .collect::<Vec<&Positioned<ConstDirective>>>();
+
+        if let Some(directive) = data_directives.get(1) {
+            self.report(
+                directive.pos,
+                ValidationErrors::MalformedDirectiveError(
+                    location.clone(),
+                    directive.node.name.node.to_string(),
+                    "a field can only have one data directive".to_string(),
+                ),
+            );
+        }
+
+        for directive in field.node.directives.iter() {
+            match directive.node.name.node.as_str() {
+                "key" => {
+                    let argument = if directive.node.get_argument("path").is_some() {
+                        "path"
+                    } else {
+                        "key"
+                    };
+                    self.string_argument(&location, directive, argument);
+                }
+                "derivedField" => {
+                    self.string_argument(&location, directive, "provider");
+                    if !data_directives.is_empty() {
+                        self.report(
+                            directive.pos,
+                            ValidationErrors::MalformedDirectiveError(
+                                location.clone(),
+                                "derivedField".to_string(),
+                                "a derived field can't have a data directive".to_string(),
+                            ),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let BaseType::List(inner) = &field.node.ty.node.base {
+            if matches!(inner.base, BaseType::List(_))
+                && self.is_node(&field.node.ty.node.entity_type())
+            {
+                self.report(
+                    field.node.ty.pos,
+                    ValidationErrors::UnsupportedError(
+                        location.clone(),
+                        "nested lists of nodes".to_string(),
+                    ),
+                );
+            }
+        }
+
+        if let Some(directive) = data_directives.first() {
+            self.validate_data_directive(type_name, &location, &field.node, directive);
+        }
+
+        self.validate_connection_source(type_name, &location, field);
+
+        self.validate_scope_directives(&location, &field.node.directives);
+    }
+
+    /// A Connection fetched with `sourceIdRequestFieldName` is sent the id of the parent of the
+    /// field, which must have one.
+    fn validate_connection_source(
+        &mut self,
+        type_name: &str,
+        location: &str,
+        field: &Positioned<FieldDefinition>,
+    ) {
+        let connection = field.node.ty.node.entity_type();
+        let has_source = self
+            .type_definition(&connection)
+            .into_iter()
+            .flat_map(|x| x.directives.iter())
+            .filter(|x| x.node.name.node.as_str() == "serviceBackedConnection")
+            .any(|x| {
+                !matches!(
+                    x.node
+                        .get_argument("sourceIdRequestFieldName")
+                        .map(|x| &x.node),
+                    None | Some(ConstValue::Null)
+                )
+            });
+
+        if has_source && !self.has_field(type_name, "id") {
+            self.report(
+                field.pos,
+                ValidationErrors::MissingSourceIdError(
+                    location.to_string(),
+                    connection,
+                    type_name.to_string(),
+                ),
+            );
+        }
+    }
+
+    /// `@scope` and `@auth`: the scopes are a list of String, checked against the claims of the
+    /// callers authenticated with the `[auth]` config.
+    fn validate_scope_directives(
+        &mut self,
+        location: &str,
+        directives: &[Positioned<ConstDirective>],
+    ) {
+        for directive in directives.iter() {
+            let name = directive.node.name.node.as_str();
+            let argument = match SCOPE_DIRECTIVES.iter().find(|(x, _)| *x == name) {
+                Some((_, argument)) => argument,
+                None => continue,
+            };
+
+            if self.config.auth().is_none() {
+                self.report(
+                    directive.pos,
+                    ValidationErrors::MissingAuthConfigError(
+                        location.to_string(),
+                        name.to_string(),
+                    ),
+                );
+            }
+
+            match directive.node.get_argument(argument) {
+                Some(Positioned {
+                    node: ConstValue::List(values),
+                    ..
+                }) if values.iter().all(|x| matches!(x, ConstValue::String(_))) => {}
+                Some(value) => self.malformed(
+                    location,
+                    value.pos,
+                    directive,
+                    format!("argument {} must be a list of String", argument),
+                ),
+                None => {}
+            }
+        }
+    }
+
+    /// `@serviceBackedQuery`, `@serviceBackedMutation` and `@serviceBackedSubscription`.
+    fn validate_data_directive(
+        &mut self,
+        type_name: &str,
+        location: &str,
+        field: &FieldDefinition,
+        directive: &Positioned<ConstDirective>,
+    ) {
+        let name = directive.node.name.node.as_str();
+        let subscription = name == "serviceBackedSubscription";
+
+        if subscription && type_name != "Subscription" {
+            self.report(
+                directive.pos,
+                ValidationErrors::MalformedDirectiveError(
+                    location.to_string(),
+                    name.to_string(),
+                    "it can only be used on fields of the Subscription type".to_string(),
+                ),
+            );
+        }
+
+        let args = match directive.node.get_argument("args") {
+            Some(args) => match args.node.clone().into_json() {
+                Ok(value @ serde_json::Value::Object(_)) => ArgumentsMapping::from_json(&value),
+                _ => {
+                    self.report(
+                        args.pos,
+                        ValidationErrors::MalformedDirectiveError(
+                            location.to_string(),
+                            name.to_string(),
+                            "args must be an object".to_string(),
+                        ),
+                    );
+                    ArgumentsMapping::default()
+                }
+            },
+            None => ArgumentsMapping::default(),
+        };
+
+        let (service, method_name) = match self.service_method(location, directive) {
+            Some(x) => x,
+            None => return,
+        };
+        let method = match self.method(location, directive.pos, &service, &method_name) {
+            Some(method) => method,
+            None => return,
+        };
+
+        if subscription && !method.is_streamable() {
+            self.report(
+                directive.pos,
+                ValidationErrors::MethodNotStreamableError(
+                    location.to_string(),
+                    service.clone(),
+                    method_name.clone(),
+                ),
+            );
+        }
+
+        let mapping = method.arguments_mapping().merge(&args);
+        for argument in method.argument_names() {
+            let nested = mapping.nested(&argument);
+            let sources = match mapping.get(&argument) {
+                Some(source) => vec![source],
+                None => nested.iter().map(|(_, source)| *source).collect(),
+            };
+
+            if sources.is_empty() {
+                // Query arguments are optional.
+                if !method.is_query_argument(&argument)
+                    && !self.has_argument(&field.arguments, &argument.to_case(Case::Snake))
+                {
+                    self.report(
+                        directive.pos,
+                        ValidationErrors::UnmatchedArgumentError(
+                            location.to_string(),
+                            argument.clone(),
+                            method_name.clone(),
+                        ),
+                    );
+                }
+                continue;
+            }
+
+            for source in sources {
+                let exists = match source {
+                    ArgumentSource::Argument(name) => {
+                        self.has_argument(&field.arguments, &name.to_case(Case::Snake))
+                    }
+                    ArgumentSource::Parent(name) => self.has_field(type_name, name),
+                    ArgumentSource::Constant(_) => true,
+                };
+
+                if !exists {
+                    self.report(
+                        directive.pos,
+                        ValidationErrors::UnknownArgumentSourceError(
+                            location.to_string(),
+                            argument.clone(),
+                            match source {
+                                ArgumentSource::Parent(name) => format!("parent.{}", name),
+                                ArgumentSource::Argument(name) => name.to_string(),
+                                ArgumentSource::Constant(value) => value.to_string(),
+                            },
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Tell if there is an argument `name`, or a required input object argument with a field
+    /// `name`, names are snake case.
+    fn has_argument(&self, arguments: &[Positioned<InputValueDefinition>], name: &str) -> bool {
+        arguments.iter().any(|x| {
+            let gql_type = &x.node.ty.node;
+            x.node.name.node.as_str().to_case(Case::Snake) == name
+                || (!gql_type.nullable
+                    && matches!(gql_type.base, BaseType::Named(_))
+                    && self
+                        .type_definition(&gql_type.entity_type())
+                        .map(|x| match &x.kind {
+                            TypeKind::InputObject(input) => input
+                                .fields
+                                .iter()
+                                .any(|x| x.node.name.node.as_str().to_case(Case::Snake) == name),
+                            _ => false,
+                        })
+                        .unwrap_or(false))
+        })
+    }
+
+    /// Tell if the type has a field `name`.
+    fn has_field(&self, type_name: &str, name: &str) -> bool {
+        self.type_definition(type_name)
+            .map(|x| match &x.kind {
+                TypeKind::Object(object) => object
+                    .fields
+                    .iter()
+                    .any(|x| x.node.name.node.as_str() == name),
+                TypeKind::Interface(interface) => interface
+                    .fields
+                    .iter()
+                    .any(|x| x.node.name.node.as_str() == name),
+                _ => false,
+            })
+            .unwrap_or(false)
+    }
+
+    /// The `service` and `methodName` arguments of a data directive.
+    fn service_method(
+        &mut self,
+        location: &str,
+        directive: &Positioned<ConstDirective>,
+    ) -> Option<(String, String)> {
+        let service = self.string_argument(location, directive, "service");
+        let method_name = self.string_argument(location, directive, "methodName");
+
+        if let Some(service) = &service {
+            self.used_services.insert(service.to_owned());
+        }
+
+        Some((service?, method_name?))
+    }
+
+    /// Get a method of a service, or report it.
+    fn method(
+        &mut self,
+        location: &str,
+        pos: Pos,
+        service: &str,
+        method_name: &str,
+    ) -> Option<Method<'a>> {
+        let config = self.config;
+        let service_config = match config.services().get(service) {
+            Some(x) => x,
+            None => {
+                self.report(
+                    pos,
+                    ValidationErrors::ServiceNotFoundError(
+                        location.to_string(),
+                        service.to_string(),
+                    ),
+                );
+                return None;
+            }
+        };
+
+        match service_config.get_a_method(service, method_name) {
+            Ok(method) => Some(method),
+            Err(_) => {
+                self.report(
+                    pos,
+                    ValidationErrors::MethodNotFoundError(
+                        location.to_string(),
+                        service.to_string(),
+                        method_name.to_string(),
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    /// Get a required String argument of a directive, or report it.
+    fn string_argument(
+        &mut self,
+        location: &str,
+        directive: &Positioned<ConstDirective>,
+        name: &str,
+    ) -> Option<String> {
+        match directive.node.get_argument(name) {
+            Some(Positioned {
+                node: ConstValue::String(value),
+                ..
+            }) => Some(value.to_owned()),
+            Some(argument) => {
+                self.malformed(
+                    location,
+                    argument.pos,
+                    directive,
+                    format!("argument {} must be a String", name),
+                );
+                None
+            }
+            None => {
+                self.malformed(
+                    location,
+                    directive.pos,
+                    directive,
+                    format!("argument {} is missing", name),
+                );
+                None
+            }
+        }
+    }
+
+    /// Check an optional String argument of a directive, or report it.
+    fn optional_string_argument(
+        &mut self,
+        location: &str,
+        directive: &Positioned<ConstDirective>,
+        name: &str,
+    ) {
+        match directive.node.get_argument(name) {
+            Some(Positioned {
+                node: ConstValue::String(_),
+                ..
+            })
+            | Some(Positioned {
+                node: ConstValue::Null,
+                ..
+            })
+            | None => {}
+            Some(argument) => self.malformed(
+                location,
+                argument.pos,
+                directive,
+                format!("argument {} must be a String", name),
+            ),
+        }
+    }
+
+    fn malformed(
+        &mut self,
+        location: &str,
+        pos: Pos,
+        directive: &Positioned<ConstDirective>,
+        reason: String,
+    ) {
+        self.report(
+            pos,
+            ValidationErrors::MalformedDirectiveError(
+                location.to_string(),
+                directive.node.name.node.to_string(),
+                reason,
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_graphql_parser::parse_schema;
+    use std::path::PathBuf;
+
+    const CONFIG: &str = r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+
+[services.stores.transport]
+type = "HTTP"
+
+[services.stores.transport.info]
+endpoint = "http://stores.io"
+
+[services.stores.transport.info.method.getStore]
+route = "stores/{id}"
+http_method = "POST"
+resilience = { retries = 2 }
+"#;
+
+    /// Diagnostics of a schema checked against `CONFIG`, as printed.
+    fn diagnostics(schema: &str) -> Vec<String> {
+        config_diagnostics(schema, CONFIG)
+    }
+
+    /// Diagnostics of a schema checked against a config, as printed.
+    fn config_diagnostics(schema: &str, config: &str) -> Vec<String> {
+        let schema_file = SourceFile {
+            path: PathBuf::from("schema.graphql"),
+            content: schema.to_string(),
+        };
+        let config_file = SourceFile {
+            path: PathBuf::from("config.toml"),
+            content: config.to_string(),
+        };
+        let schema = parse_schema(schema).unwrap();
+        let config = toml::from_str::<Config>(config).unwrap();
+
+        check(&schema_file, &schema, &config_file, &config)
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_schema() {
+        let diagnostics = diagnostics(
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+  store(id: ID!): Store @serviceBackedQuery(service: "stores", methodName: "getStore")
+}
+
+type Pet {
+  id: ID!
+}
+
+type Store {
+  id: ID!
+}
+"#,
+        );
+
+        // Retries of a POST are ignored, it's only a warning.
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert!(diagnostics[0].starts_with(
+            "warning: stores.getStore: POST calls are not idempotent, they are not retried\n"
+        ));
+        assert!(diagnostics[0].contains("--> config.toml:12:1"));
+    }
+
+    #[test]
+    fn test_located_errors() {
+        let diagnostics = diagnostics(
+            r#"type Query {
+  pet(petId: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+  owner: Owner @serviceBackedQuery(service: "owners", methodName: "getOwner")
+}
+
+type Pet {
+  id: ID!
+}
+"#,
+        );
+        let errors = diagnostics
+            .iter()
+            .filter(|x| x.starts_with("error: "))
+            .collect::<Vec<_>>();
+
+        assert_eq!(errors.len(), 3, "{:?}", diagnostics);
+        assert!(errors.iter().any(|x| x.starts_with(
+            "error: Query.pet: argument id of method getPet matches no GraphQL argument\n"
+        ) && x.contains("--> schema.graphql:2:")));
+        assert!(errors.iter().any(|x| x
+            .starts_with("error: Query.owner: type Owner is not defined\n")
+            && x.contains("--> schema.graphql:3:")));
+        assert!(errors
+            .iter()
+            .any(|x| x.starts_with("error: Query.owner: service owners not found\n")));
+    }
+
+    #[test]
+    fn test_node_batch() {
+        let diagnostics = diagnostics(
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Pet @serviceBackedNode(service: "pets", methodName: "getPet") {
+  id: ID!
+}
+"#,
+        );
+
+        assert!(
+            diagnostics.iter().any(|x| x.starts_with(
+                "error: Pet: method getPet of service pets can't load a batch of nodes, its argument id is inside the route\n"
+            ) && x.contains("--> schema.graphql:5:")),
+            "{:?}",
+            diagnostics
+        );
+    }
+
+    #[test]
+    fn test_breaker_without_failures() {
+        let diagnostics = config_diagnostics(
+            r#"type Query {
+  pet(id: ID!): Pet @serviceBackedQuery(service: "pets", methodName: "getPet")
+}
+
+type Pet {
+  id: ID!
+}
+"#,
+            r#"
+[services.pets.transport]
+type = "HTTP"
+
+[services.pets.transport.info]
+endpoint = "http://pets.io"
+
+[services.pets.transport.info.method.getPet]
+route = "pets/{id}"
+http_method = "GET"
+resilience = { breaker = { failures = 0 } }
+"#,
+        );
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert!(diagnostics[0].starts_with(
+            "error: pets.getPet: the circuit breaker must open after at least one failure\n"
+        ));
+    }
+}
@@ -1,9 +1,46 @@
 mod app;
 mod codegen;
 
+use codegen::generate::GenericErrors;
+
+/// Print an error and exit, diagnostics are printed compiler-style.
+fn exit_with(error: GenericErrors) -> ! {
+    match error {
+        GenericErrors::DiagnosticsError(diagnostics) => {
+            for diagnostic in diagnostics.iter() {
+                eprintln!("{}", diagnostic);
+            }
+            eprintln!("error: {} problem(s) found", diagnostics.len());
+        }
+        error => eprintln!("error: {}", error),
+    }
+    std::process::exit(1);
+}
+
 fn main() {
     let app = app::build_app().get_matches();
 
+    if let Some(validate) = app.subcommand_matches("validate") {
+        let schema = validate.value_of("schema").unwrap();
+        let config = validate.value_of("config").unwrap();
+
+        let (errors, warnings): (Vec<_>, Vec<_>) = codegen::validate::validate(schema, config)
+            .unwrap_or_else(|e| exit_with(e))
+            .into_iter()
+            .partition(|x| x.is_error());
+
+        for warning in warnings.iter() {
+            println!("{}", warning);
+        }
+
+        if !errors.is_empty() {
+            exit_with(GenericErrors::DiagnosticsError(errors));
+        }
+
+        println!("The schema is valid");
+        return;
+    }
+
     let schema = app.value_of("schema").unwrap();
     let output = app.value_of("output").unwrap();
     let config = app.value_of("config").unwrap();
@@ -12,9 +49,5 @@ fn main() {
     // Parse schema
     // Create a general context
     // Render the document
-    codegen::generate::generate(schema, output, config)
-        .map_err(|e| {
-            println!("Error: {:?}", e);
-        })
-        .unwrap();
+    codegen::generate::generate(schema, output, config).unwrap_or_else(|e| exit_with(e));
 }